@@ -0,0 +1,64 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lightningcss::{
+    css_modules::{Config, Pattern},
+    printer::PrinterOptions,
+    stylesheet::{ParserOptions, StyleSheet},
+};
+use swc_plugin_react_css_modules::css_exports::collect_exports;
+
+/// A stylesheet with `rules` rules, similar to a component library's
+fn large_stylesheet(rules: usize) -> String {
+    (0..rules)
+        .map(|i| {
+            format!(
+                ".block-{i} {{ display: flex; padding: {i}px 4px; color: #{:06x}; }}\n\
+                 .block-{i}:hover > .element-{i}, .block-{i}.is-active {{ color: red; }}\n\
+                 .element-{i} {{ composes: block-{}; }}\n\
+                 @media (min-width: {}px) {{ .element-{i} {{ margin: 0 auto; animation: fade-{i} 1s; }} }}\n\
+                 @keyframes fade-{i} {{ from {{ opacity: 0; }} to {{ opacity: 1; }} }}\n",
+                i * 97 % 0xffffff,
+                i / 2,
+                320 + i,
+            )
+        })
+        .collect()
+}
+
+fn parse(source: &str) -> StyleSheet<'_, '_> {
+    StyleSheet::parse(
+        source,
+        ParserOptions {
+            filename: "styles.css".to_string(),
+            css_modules: Some(Config {
+                pattern: Pattern::parse("[local]").unwrap(),
+                dashed_idents: false,
+            }),
+            ..ParserOptions::default()
+        },
+    )
+    .unwrap()
+}
+
+fn bench_exports(c: &mut Criterion) {
+    for rules in [100, 1000] {
+        let source = large_stylesheet(rules);
+        let mut stylesheet = parse(&source);
+        let mut group = c.benchmark_group(format!("exports/{}-rules", rules));
+
+        group.bench_function("printer", |b| {
+            b.iter(|| {
+                black_box(&stylesheet)
+                    .to_css(PrinterOptions::default())
+                    .unwrap()
+                    .exports
+            })
+        });
+        group.bench_function("visitor", |b| {
+            b.iter(|| collect_exports(black_box(&mut stylesheet), false).unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_exports);
+criterion_main!(benches);
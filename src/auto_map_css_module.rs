@@ -1,70 +1,188 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use path_absolutize::*;
 use swc_core::{
-    common::{Span, Spanned, DUMMY_SP},
+    common::{BytePos, Span, Spanned, DUMMY_SP},
     ecma::{
         ast::{
             BindingIdent, CallExpr, Callee, Decl, Expr, ExprOrSpread, Ident, ImportDecl,
             ImportDefaultSpecifier, ImportPhase, ImportSpecifier, JSXAttr, JSXAttrName,
             JSXAttrOrSpread, JSXAttrValue, JSXExpr, JSXExprContainer, JSXOpeningElement,
-            KeyValueProp, Lit, Module, ModuleDecl, ModuleItem, ObjectLit, Pat, Prop, PropName,
-            PropOrSpread, Stmt, Str, Tpl, TplElement, VarDecl, VarDeclKind, VarDeclarator,
+            KeyValueProp, Lit, Module, ModuleDecl, ModuleExportName, ModuleItem, ObjectLit, Pat,
+            Prop, PropName, PropOrSpread, SpreadElement, Stmt, Str, Tpl, TplElement, VarDecl,
+            VarDeclKind, VarDeclarator,
         },
         atoms::JsWord,
         visit::{VisitMut, VisitMutWith},
     },
-    plugin::errors::HANDLER,
 };
 
-use crate::{process_stylesheet::CssModuleParser, Config};
+use crate::{
+    cache::DiskCache,
+    css_exports::{ExportKind, ExportKinds},
+    diagnostics::{self, DiagnosticCode, Severity},
+    loader_utils::interpolate::parse_resource,
+    manifest::{self, Manifest},
+    process_stylesheet::{CssModuleParser, ParsedStylesheet},
+    Config, Error,
+};
 
 pub struct AutoMapCssModules {
-    /// holds the directory of the file being processed
-    dir: PathBuf,
+    /// holds the directory and the virtual directory of the file being processed,
+    /// or the reason they could not be determined
+    dirs: Result<(PathBuf, PathBuf), Error>,
 
-    /// holds the virtual directory of the file being processed
-    virtual_dir: PathBuf,
-
-    /// project root
+    /// project root, stylesheets are resolved from here
+    /// hashes use `Config::local_ident_context` instead
     context: PathBuf,
 
     /// holds the configuration for the plugin
     config: Config,
 
+    /// class maps stored by previous builds, if `cache_dir` is set
+    disk_cache: Option<DiskCache>,
+
+    /// class maps written by the css build, if `class_map_source` is set,
+    /// or the reason the manifest could not be read
+    manifest: Option<Result<Arc<Manifest>, Error>>,
+
     /// holds the mapping of style names to generated class names
     style_maps_for_file: HashMap<JsWord, HashMap<String, String>>,
 
+    /// what each name of `style_maps_for_file` is used for
+    kinds_for_file: HashMap<JsWord, ExportKinds>,
+
+    /// local names of the `scopedName` macro imports
+    macro_names: Vec<JsWord>,
+
+    /// macros still referenced after the expansion, e.g. by a rejected call,
+    /// their import is kept
+    kept_macro_names: HashSet<JsWord>,
+
+    /// holds the import name and source of each stylesheet, in import order
+    imports: Vec<(JsWord, String)>,
+
     /// flag to determine if the runtime helper should be injected
     is_runtime_helper_req: bool,
 }
 
+/// module exporting the compile-time lookups, its imports are removed once expanded
+const MACRO_SRC: &str = "swc-plugin-react-css-modules/dist/macro";
+
+/// attribute resolved to class names regardless of `Config::attribute_names`
+const STYLE_NAME_ATTRIBUTE: (&str, &str) = ("styleName", "className");
+
+/// attribute whose keys are resolved to scoped custom properties, merged into `style`
+const STYLE_VARS_ATTRIBUTE: &str = "styleVars";
+
+/// swc allows fs access only under /cwd alias
+/// https://github.com/swc-project/swc/pull/4279
+fn is_virtualized() -> bool {
+    std::fs::metadata("/cwd").is_ok()
+}
+
+/// Returns the path `path` can be accessed with, `None` if it is outside of root
+/// in a virtualized fs.
+pub(crate) fn get_fs_path(context: &Path, path: PathBuf) -> Option<PathBuf> {
+    if !is_virtualized() {
+        return Some(path);
+    }
+    virtual_path(context, &path)
+}
+
+/// Returns `path` under the `/cwd` alias, `None` if it is outside of root
+pub(crate) fn virtual_path(context: &Path, path: &Path) -> Option<PathBuf> {
+    path.strip_prefix(context)
+        .ok()
+        .map(|relative_path| PathBuf::from("/cwd").join(relative_path))
+}
+
 /// Returns the full path to the file's directory.
 ///
 /// - swc/loader and swc/jest pass full `filepath`
 /// - swc/cli pass relative `filepath`
-fn get_dirs(mut context: PathBuf, filepath: PathBuf) -> (PathBuf, PathBuf) {
-    // swc allows fs access only under /cwd alias
-    // https://github.com/swc-project/swc/pull/4279
+fn get_dirs(mut context: PathBuf, filepath: PathBuf) -> Result<(PathBuf, PathBuf), Error> {
     // this check is to enure tests still work
     // TODO: figure out a better way to distinguish `cargo test`
-    let mut virtual_dir = if std::fs::metadata("/cwd").is_ok() {
+    let is_virtualized = is_virtualized();
+    let mut virtual_dir = if is_virtualized {
         PathBuf::from("/cwd")
     } else {
         context.clone()
     };
 
-    let file_dir = filepath.parent().unwrap().to_path_buf();
+    let file_dir = filepath.parent().unwrap_or(&filepath).to_path_buf();
 
     // If the filepath has a root, it's already the full path.
     if file_dir.has_root() {
-        virtual_dir.push(file_dir.strip_prefix(context.clone()).unwrap());
-        return (file_dir, virtual_dir);
+        match file_dir.strip_prefix(&context) {
+            Ok(relative_dir) => virtual_dir.push(relative_dir),
+            // without a virtualized fs, files outside of root can still be read
+            Err(_) if !is_virtualized => virtual_dir = file_dir.clone(),
+            Err(_) => {
+                return Err(Error::OutsideRoot {
+                    path: filepath,
+                    root: context,
+                })
+            }
+        }
+        return Ok((file_dir, virtual_dir));
     }
 
     context.push(file_dir.clone());
     virtual_dir.push(file_dir);
-    (context, virtual_dir)
+    Ok((context, virtual_dir))
+}
+
+/// Levenshtein distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Returns `name` of `:global(name)` and `global.name`, classes of global stylesheets
+/// that are used as is, see `getClassName.ts` for the runtime counterpart.
+/// `global.name` names a class of the import when a stylesheet is imported as `global`
+fn global_escape(style_name: &str, global_import: bool) -> Option<&str> {
+    style_name
+        .strip_prefix(":global(")
+        .and_then(|name| name.strip_suffix(')'))
+        .or_else(|| {
+            style_name
+                .strip_prefix("global.")
+                .filter(|_| !global_import)
+        })
+        .filter(|name| !name.is_empty())
+}
+
+/// Returns the span of `token` inside the string literal `str_lit`,
+/// falls back to the span of the literal when the raw source contains escapes.
+fn token_span(str_lit: &Str, token: &str) -> Span {
+    let value: &str = &str_lit.value;
+    let offset = token.as_ptr() as usize - value.as_ptr() as usize;
+
+    match &str_lit.raw {
+        Some(raw) if raw.len() == value.len() + 2 && &raw[1..raw.len() - 1] == value => {
+            let lo = str_lit.span.lo + BytePos(1 + offset as u32);
+            Span::new(lo, lo + BytePos(token.len() as u32), str_lit.span.ctxt)
+        }
+        _ => str_lit.span,
+    }
 }
 
 impl AutoMapCssModules {
@@ -75,102 +193,613 @@ impl AutoMapCssModules {
             config.root.clone()
         });
 
-        let (dir, virtual_dir) = get_dirs(context.clone(), PathBuf::from(filepath));
+        let dirs = get_dirs(context.clone(), PathBuf::from(filepath));
+        let disk_cache = config
+            .cache_dir_in(&context)
+            .and_then(|cache_dir| get_fs_path(&context, cache_dir))
+            .map(DiskCache::new);
+        let manifest = config.manifest_in(&context).map(|full_path| {
+            let fs_path =
+                get_fs_path(&context, full_path.clone()).ok_or_else(|| Error::OutsideRoot {
+                    path: full_path.clone(),
+                    root: context.clone(),
+                })?;
+            manifest::load(&fs_path, &full_path)
+        });
 
         Self {
-            dir,
-            virtual_dir,
+            dirs,
             context,
             config: config.clone(),
+            disk_cache,
+            manifest,
             style_maps_for_file: HashMap::new(),
+            kinds_for_file: HashMap::new(),
+            macro_names: Vec::new(),
+            kept_macro_names: HashSet::new(),
+            imports: Vec::new(),
             is_runtime_helper_req: false,
         }
     }
 
-    fn add_import(&mut self, name: &JsWord, src: &JsWord) {
-        let src_path = PathBuf::from(src.to_string());
+    fn add_import(&mut self, name: &JsWord, src: &JsWord, span: Span) {
+        self.imports.push((name.clone(), src.to_string()));
+        match self.generate_style_name_map(src) {
+            Ok(parsed) => {
+                for diagnostic in &parsed.diagnostics {
+                    self.report(span, diagnostic);
+                }
+                self.style_maps_for_file
+                    .insert(name.clone(), parsed.style_name_map);
+                self.kinds_for_file.insert(name.clone(), parsed.kinds);
+            }
+            Err(err) => self.report(span, &err),
+        }
+    }
+
+    fn generate_style_name_map(&self, src: &JsWord) -> Result<ParsedStylesheet, Error> {
+        let (dir, virtual_dir) = self.dirs.clone()?;
+        // `./a.css?inline#top` is read from `./a.css`
+        let (path, query, fragment) = parse_resource(src);
+        let src_path = PathBuf::from(path);
+        let unresolved = |path: PathBuf| Error::UnresolvedImport {
+            src: src.to_string(),
+            path,
+        };
 
         let file_path = src_path
-            .absolutize_from(self.dir.clone())
-            .unwrap()
+            .absolutize_from(&dir)
+            .map_err(|_| unresolved(src_path.clone()))?
             .to_path_buf();
 
         if !file_path.has_root() {
-            panic!(
-                "src_path: {}; file_path: {}",
-                src_path.to_str().unwrap(),
-                file_path.to_str().unwrap()
-            )
+            return Err(unresolved(file_path));
         }
 
         let virtual_path = src_path
-            .absolutize_from(self.virtual_dir.clone())
-            .unwrap()
+            .absolutize_from(&virtual_dir)
+            .map_err(|_| unresolved(src_path.clone()))?
             .to_path_buf();
 
         let css_parser = CssModuleParser::new(
-            self.config.generate_scoped_name.clone(),
-            self.context.clone(),
-            self.config.hash_prefix.clone(),
+            &self.config,
+            &self.context,
             virtual_path,
-            file_path,
-        );
+            file_path.clone(),
+            self.disk_cache.as_ref(),
+        )
+        .with_resource_query(query, fragment);
 
-        let style_name_map = css_parser.generate_style_name_map();
-        match style_name_map {
-            Ok(style_name_map) => {
-                self.style_maps_for_file
-                    .insert(name.clone(), style_name_map);
+        match &self.manifest {
+            Some(manifest) => {
+                let manifest = manifest.clone()?;
+                self.manifest_style_name_map(&manifest, &file_path, css_parser)
             }
-            Err(err_str) => HANDLER.with(|handler| handler.struct_err(&err_str).emit()),
+            None => css_parser.generate_style_name_map(),
         }
     }
 
-    fn get_generated_name(&self, style_name: &str, span: &Span) -> String {
-        let mut style_name_parts: Vec<&str> = style_name.splitn(2, ".").collect();
+    /// Looks up the class map of the stylesheet at `file_path` in the manifest,
+    /// with `verify` the stylesheet is compiled and the names differing are reported
+    fn manifest_style_name_map(
+        &self,
+        manifest: &Manifest,
+        file_path: &Path,
+        css_parser: CssModuleParser,
+    ) -> Result<ParsedStylesheet, Error> {
+        let key = manifest::key_of(file_path, &self.context);
+        let missing = || Error::MissingManifestEntry {
+            manifest: self.config.manifest_in(&self.context).unwrap_or_default(),
+            key: key.clone(),
+        };
+        let style_name_map = manifest.get(&key).cloned().ok_or_else(missing)?;
+        let mut parsed = ParsedStylesheet {
+            style_name_map,
+            diagnostics: Vec::new(),
+            dependencies: Vec::new(),
+            kinds: ExportKinds::new(),
+        };
 
-        let generated_name_opt = match style_name_parts.len() {
+        let verify = self
+            .config
+            .class_map_source
+            .as_ref()
+            .is_some_and(|source| source.verify);
+        if verify {
+            match css_parser.generate_style_name_map() {
+                Ok(generated) => {
+                    parsed.diagnostics =
+                        manifest::mismatches(&key, &parsed.style_name_map, &generated);
+                    parsed.diagnostics.splice(0..0, generated.diagnostics);
+                    parsed.kinds = generated.kinds;
+                }
+                Err(err) => parsed.diagnostics.push(err),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    fn get_generated_name(&self, style_name: &str, span: &Span) -> Option<String> {
+        let global_import = self
+            .style_maps_for_file
+            .contains_key(&JsWord::from("global"));
+        if let Some(global_name) = global_escape(style_name, global_import) {
+            return Some(global_name.to_string());
+        }
+        self.resolve_style_name(style_name, span)
+            .map(|(_, generated_name)| generated_name)
+    }
+
+    /// Returns the import defining `style_name` along with its generated name,
+    /// reports `style_name` if it can not be resolved
+    fn resolve_style_name(&self, style_name: &str, span: &Span) -> Option<(JsWord, String)> {
+        let generated_name_opt = match self.split_style_name(style_name) {
             // without prefix, ie styleName="foo-bar"
-            1 => {
+            (None, style_name) => {
                 let no_prefix_name = match self.style_maps_for_file.get(&JsWord::from("")) {
-                    Some(style_map) => style_map.get(&style_name.to_string()),
+                    Some(style_map) => style_map
+                        .get(&style_name.to_string())
+                        .map(|generated_name| (JsWord::from(""), generated_name)),
                     None => None,
                 };
                 if no_prefix_name.is_none() {
-                    self.style_maps_for_file
+                    let matches: Vec<(&JsWord, &String, &String)> = self
+                        .imports
                         .iter()
-                        .find_map(|(_, v)| v.get(&style_name.to_string()))
+                        .filter_map(|(import, src)| {
+                            self.style_maps_for_file
+                                .get(import)
+                                .and_then(|v| v.get(style_name))
+                                .map(|generated_name| (import, src, generated_name))
+                        })
+                        .collect();
+                    if matches.len() > 1 {
+                        self.report(
+                            *span,
+                            &Error::AmbiguousStyleName {
+                                name: style_name.to_string(),
+                                sources: matches
+                                    .iter()
+                                    .map(|(_, src, _)| src.to_string())
+                                    .collect(),
+                            },
+                        );
+                    }
+                    matches
+                        .first()
+                        .map(|(import, _, generated_name)| ((*import).clone(), *generated_name))
                 } else {
                     no_prefix_name
                 }
             }
             // with prefix,ie styleName="styles.foo-bar"
-            2 => {
-                let module = style_name_parts.remove(0);
-                let name = style_name_parts.remove(0);
+            (Some(module), name) => match self.style_maps_for_file.get(&JsWord::from(module)) {
+                Some(style_map) => style_map
+                    .get(name)
+                    .map(|generated_name| (JsWord::from(module), generated_name)),
+                None => {
+                    self.report(*span, &self.unknown_prefix_error(module, name));
+                    return None;
+                }
+            },
+        };
 
-                match self.style_maps_for_file.get(&JsWord::from(module)) {
-                    Some(style_map) => style_map.get(name),
-                    None => None,
+        match generated_name_opt {
+            Some((import, generated_name)) => {
+                if self
+                    .kinds_of(&import, style_name)
+                    .is_some_and(|kinds| *kinds == [ExportKind::GlobalClass])
+                {
+                    let src = self.imports.iter().find(|(name, _)| *name == import);
+                    self.report(
+                        *span,
+                        &Error::GlobalStyleName {
+                            name: style_name.to_string(),
+                            src: src.map(|(_, src)| src.clone()).unwrap_or_default(),
+                        },
+                    );
                 }
+                Some((import, generated_name.to_string()))
+            }
+            None => {
+                self.report(*span, &self.unresolved_error(style_name));
+                None
+            }
+        }
+    }
+
+    /// Splits `style_name` into its import prefix and class name, escaped class names
+    /// such as `mt-2.5` are only split when the prefix is an import or they are not a class
+    fn split_style_name<'s>(&self, style_name: &'s str) -> (Option<&'s str>, &'s str) {
+        match style_name.split_once('.') {
+            Some((module, name))
+                if self.style_maps_for_file.contains_key(&JsWord::from(module))
+                    || !self
+                        .style_maps_for_file
+                        .values()
+                        .any(|style_map| style_map.contains_key(style_name)) =>
+            {
+                (Some(module), name)
             }
-            // more than 1 dot in name
+            _ => (None, style_name),
+        }
+    }
+
+    /// Kinds of `style_name`, prefixed or not, in the stylesheet imported as `import`
+    fn kinds_of(&self, import: &JsWord, style_name: &str) -> Option<&Vec<ExportKind>> {
+        let (_, name) = self.split_style_name(style_name);
+        self.kinds_for_file.get(import)?.get(name)
+    }
+
+    /// Returns the local name of the macro called by `call`, if any
+    fn macro_name_of(&self, call: &CallExpr) -> Option<JsWord> {
+        match &call.callee {
+            Callee::Expr(callee) => match &**callee {
+                Expr::Ident(ident) if self.macro_names.contains(&ident.sym) => {
+                    Some(ident.sym.clone())
+                }
+                _ => None,
+            },
             _ => None,
+        }
+    }
+
+    /// Replaces `scopedName("name")` or `scopedName("name", "kind")` with the generated name,
+    /// the call is kept if its arguments are not string literals
+    fn expand_macro_call(&self, macro_name: &JsWord, call: &CallExpr) -> Option<Expr> {
+        let invalid = |reason: &str| {
+            self.report(
+                call.span,
+                &Error::InvalidMacroCall {
+                    name: macro_name.to_string(),
+                    reason: reason.to_string(),
+                },
+            );
+            None
         };
 
-        match generated_name_opt {
-            Some(generated_name) => generated_name.to_string(),
-            None => {
-                // TODO: replace with warning in build
-                HANDLER.with(|handler| {
-                    handler.struct_span_err(
-                            *span,
-                            &format!("Could not resolve styleName {}", style_name),
-                        ).emit();
-                });
-                String::default()
+        let mut args = Vec::new();
+        for arg in &call.args {
+            match (&arg.spread, &*arg.expr) {
+                (None, Expr::Lit(Lit::Str(str_lit))) => args.push(str_lit),
+                _ => return invalid("arguments must be string literals"),
             }
         }
+        let (name, kind) = match args.as_slice() {
+            [name] => (*name, None),
+            [name, kind] => match ExportKind::from_name(&kind.value) {
+                Some(expected) => (*name, Some(expected)),
+                None => {
+                    return invalid(&format!(
+                        "unknown kind {:?}, expected one of class, id, keyframes, custom-ident, dashed-ident, global-class or value",
+                        &*kind.value
+                    ))
+                }
+            },
+            _ => return invalid("expected a name and an optional kind"),
+        };
+
+        let generated_name = match self.resolve_style_name(&name.value, &name.span) {
+            Some((import, generated_name)) => {
+                let found = self.kinds_of(&import, &name.value);
+                if let (Some(expected), Some(found)) = (kind, found) {
+                    if !found.contains(&expected) {
+                        self.report(
+                            name.span,
+                            &Error::ExportKindMismatch {
+                                name: name.value.to_string(),
+                                expected,
+                                found: found.clone(),
+                            },
+                        );
+                    }
+                }
+                generated_name
+            }
+            None => String::default(),
+        };
+        Some(Expr::Lit(Lit::Str(Str {
+            span: call.span,
+            value: generated_name.into(),
+            raw: None,
+        })))
+    }
+
+    /// Reports `err` at `span` with the severity configured for its code
+    fn report(&self, span: Span, err: &Error) {
+        diagnostics::report(span, err, self.config.severity_of(err.code()));
+    }
+
+    fn unknown_prefix_error(&self, prefix: &str, name: &str) -> Error {
+        Error::UnknownImportPrefix {
+            prefix: prefix.to_string(),
+            name: name.to_string(),
+            imports: self
+                .imports
+                .iter()
+                .filter(|(import, _)| !import.is_empty())
+                .map(|(import, _)| import.to_string())
+                .collect(),
+        }
+    }
+
+    /// Lists the stylesheets searched for `style_name` and suggests the closest class name
+    fn unresolved_error(&self, style_name: &str) -> Error {
+        let (searched, name): (Vec<&(JsWord, String)>, &str) = match style_name.split_once('.') {
+            Some((prefix, name)) => (
+                self.imports
+                    .iter()
+                    .filter(|(import, _)| import == prefix)
+                    .collect(),
+                name,
+            ),
+            None => (self.imports.iter().collect(), style_name),
+        };
+
+        let mut suggestion: Option<(usize, String, String)> = None;
+        for (import, src) in searched.iter() {
+            let Some(style_map) = self.style_maps_for_file.get(import) else {
+                continue;
+            };
+            let mut candidates: Vec<&String> = style_map.keys().collect();
+            candidates.sort();
+            for candidate in candidates {
+                let distance = edit_distance(name, candidate);
+                if distance <= (name.chars().count() / 3).max(2)
+                    && suggestion
+                        .as_ref()
+                        .is_none_or(|(best, ..)| distance < *best)
+                {
+                    let qualified = if import.is_empty() || !style_name.contains('.') {
+                        candidate.clone()
+                    } else {
+                        format!("{}.{}", import, candidate)
+                    };
+                    suggestion = Some((distance, qualified, src.clone()));
+                }
+            }
+        }
+
+        Error::UnresolvedStyleName {
+            name: style_name.to_string(),
+            searched: searched.iter().map(|(_, src)| src.clone()).collect(),
+            suggestion: suggestion.map(|(_, name, src)| (name, src)),
+        }
+    }
+
+    /// Replaces the `source` attribute of `n` with scoped names merged into the `target` attribute
+    fn map_attribute(&mut self, n: &mut JSXOpeningElement, source: &str, target: &str) {
+        let mut class_names: Option<JSXAttr> = None;
+        let mut style_names: Option<JSXAttr> = None;
+
+        for attr in n.attrs.iter() {
+            match attr {
+                JSXAttrOrSpread::JSXAttr(jsx_attr) => {
+                    if let JSXAttrName::Ident(Ident { sym, .. }) = &jsx_attr.name {
+                        if sym == source {
+                            style_names = Some(jsx_attr.clone());
+                        } else if sym == target {
+                            class_names = Some(jsx_attr.clone());
+                        }
+                    }
+                }
+                JSXAttrOrSpread::SpreadElement(_) => {}
+            }
+        }
+
+        if style_names.is_none() {
+            return;
+        }
+
+        // delete the source and target attributes, as they will be replaced
+        n.attrs.retain(|attr| match attr {
+            JSXAttrOrSpread::JSXAttr(jsx_attr) => {
+                if let JSXAttrName::Ident(Ident { sym, .. }) = &jsx_attr.name {
+                    sym != target && sym != source
+                } else {
+                    true
+                }
+            }
+            JSXAttrOrSpread::SpreadElement(_) => true,
+        });
+
+        if let Some(style_names) = style_names {
+            match &style_names.value {
+                // styleName="style1 foo.style2"
+                Some(JSXAttrValue::Lit(Lit::Str(str_lit_val))) => {
+                    // unresolved names are reported and left out
+                    let generated_names: Vec<String> = str_lit_val
+                        .value
+                        .split_whitespace()
+                        .filter_map(|style_name| {
+                            self.get_generated_name(
+                                style_name,
+                                &token_span(str_lit_val, style_name),
+                            )
+                        })
+                        .filter(|generated_name| !generated_name.is_empty())
+                        .collect();
+                    if generated_names.is_empty() {
+                        n.attrs.extend(class_names.map(JSXAttrOrSpread::JSXAttr));
+                    } else {
+                        update_element_class_names(
+                            n,
+                            target,
+                            class_names,
+                            generated_names.join(" "),
+                        );
+                    }
+                }
+                // styleName={style3}
+                Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+                    expr: JSXExpr::Expr(expr),
+                    ..
+                })) => {
+                    self.report(expr.span(), &Error::RuntimeFallback);
+                    if self.config.severity_of(DiagnosticCode::RuntimeFallback) == Severity::Error {
+                        // keep className as is, the build fails anyway
+                        if let Some(class_names) = class_names {
+                            n.attrs.push(JSXAttrOrSpread::JSXAttr(class_names));
+                        }
+                    } else {
+                        self.is_runtime_helper_req = true;
+                        update_element_class_names_with_expr(n, target, class_names, expr);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Replaces `styleVars={{ accent: color }}` with `style={{ '--scoped-accent': color }}`,
+    /// merged into the existing `style` attribute
+    fn map_style_vars(&mut self, n: &mut JSXOpeningElement) {
+        let Some(style_vars) = take_attribute(n, STYLE_VARS_ATTRIBUTE) else {
+            return;
+        };
+        let object = match style_vars.value {
+            Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+                expr: JSXExpr::Expr(expr),
+                ..
+            })) => match *expr {
+                Expr::Object(object) => object,
+                expr => {
+                    let reason = "expected an object literal";
+                    self.report(expr.span(), &Error::InvalidStyleVars { reason });
+                    return;
+                }
+            },
+            _ => {
+                let reason = "expected an object literal";
+                self.report(style_vars.span, &Error::InvalidStyleVars { reason });
+                return;
+            }
+        };
+
+        let mut custom_properties = Vec::new();
+        for prop in object.props {
+            let (key, key_span, value) = match prop {
+                PropOrSpread::Prop(prop) => match *prop {
+                    Prop::KeyValue(KeyValueProp {
+                        key: PropName::Ident(ident),
+                        value,
+                    }) => (ident.sym.to_string(), ident.span, value),
+                    Prop::KeyValue(KeyValueProp {
+                        key: PropName::Str(str_lit),
+                        value,
+                    }) => (str_lit.value.to_string(), str_lit.span, value),
+                    Prop::Shorthand(ident) => (
+                        ident.sym.to_string(),
+                        ident.span,
+                        Box::new(Expr::Ident(ident)),
+                    ),
+                    prop => {
+                        let reason = "keys must be identifiers or string literals";
+                        self.report(prop.span(), &Error::InvalidStyleVars { reason });
+                        continue;
+                    }
+                },
+                // merged as is rather than dropped: its keys are not scoped, but it may hold
+                // properties that are already, e.g. `--button__accent`, or plain styles
+                PropOrSpread::Spread(spread) => {
+                    let reason = "spread properties can not be resolved at compile time";
+                    self.report(spread.expr.span(), &Error::InvalidStyleVars { reason });
+                    custom_properties.push(PropOrSpread::Spread(spread));
+                    continue;
+                }
+            };
+
+            // `accent` and `styles.accent` refer to `--accent`
+            let style_name = match key.split_once('.') {
+                Some((prefix, name)) => format!("{}.{}", prefix, dashed_name(name)),
+                None => dashed_name(&key),
+            };
+            if let Some((_, generated_name)) = self.resolve_style_name(&style_name, &key_span) {
+                custom_properties.push(PropOrSpread::Prop(Box::new(Prop::KeyValue(
+                    KeyValueProp {
+                        key: PropName::Str(generated_name.into()),
+                        value,
+                    },
+                ))));
+            }
+        }
+        // nothing resolved, `style` is left as it is
+        if custom_properties.is_empty() {
+            return;
+        }
+
+        let style = match take_attribute(n, "style") {
+            // style={{ color }}
+            Some(JSXAttr {
+                value:
+                    Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+                        expr: JSXExpr::Expr(expr),
+                        ..
+                    })),
+                ..
+            }) => match *expr {
+                Expr::Object(mut object) => {
+                    object.props.extend(custom_properties);
+                    object
+                }
+                // style={style}
+                expr => {
+                    let mut props = vec![PropOrSpread::Spread(SpreadElement {
+                        dot3_token: DUMMY_SP,
+                        expr: Box::new(expr),
+                    })];
+                    props.extend(custom_properties);
+                    ObjectLit {
+                        span: DUMMY_SP,
+                        props,
+                    }
+                }
+            },
+            Some(style) => {
+                let reason = "`style` has to be an expression to be merged with";
+                self.report(style.span, &Error::InvalidStyleVars { reason });
+                n.attrs.push(JSXAttrOrSpread::JSXAttr(style));
+                return;
+            }
+            None => ObjectLit {
+                span: DUMMY_SP,
+                props: custom_properties,
+            },
+        };
+        n.attrs.push(JSXAttrOrSpread::JSXAttr(JSXAttr {
+            span: DUMMY_SP,
+            name: JSXAttrName::Ident(Ident {
+                span: DUMMY_SP,
+                sym: "style".into(),
+                optional: false,
+            }),
+            value: Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+                span: DUMMY_SP,
+                expr: JSXExpr::Expr(Box::new(Expr::Object(style))),
+            })),
+        }));
+    }
+
+    /// Removes the `scopedName` imports whose calls were all expanded,
+    /// the other imports of the macro module are kept
+    fn remove_expanded_macro_imports(&self, n: &mut Module) {
+        n.body.retain_mut(|module_item| {
+            let ModuleItem::ModuleDecl(ModuleDecl::Import(import)) = module_item else {
+                return true;
+            };
+            if import.src.value != MACRO_SRC || import.specifiers.is_empty() {
+                return true;
+            }
+            import.specifiers.retain(|specifier| match specifier {
+                ImportSpecifier::Named(named) => {
+                    !self.macro_names.contains(&named.local.sym)
+                        || self.kept_macro_names.contains(&named.local.sym)
+                }
+                _ => true,
+            });
+            !import.specifiers.is_empty()
+        });
     }
 
     /// Returns the styleName object declaration \
@@ -180,6 +809,30 @@ impl AutoMapCssModules {
     }
 }
 
+/// Removes the attribute named `name` from `n` and returns it
+fn take_attribute(n: &mut JSXOpeningElement, name: &str) -> Option<JSXAttr> {
+    let index = n.attrs.iter().position(|attr| match attr {
+        JSXAttrOrSpread::JSXAttr(JSXAttr {
+            name: JSXAttrName::Ident(ident),
+            ..
+        }) => ident.sym == name,
+        _ => false,
+    })?;
+    match n.attrs.remove(index) {
+        JSXAttrOrSpread::JSXAttr(attr) => Some(attr),
+        JSXAttrOrSpread::SpreadElement(_) => None,
+    }
+}
+
+/// Prefixes `name` with the dashes of a custom property, if it does not have them yet
+fn dashed_name(name: &str) -> String {
+    if name.starts_with("--") {
+        name.to_string()
+    } else {
+        format!("--{}", name)
+    }
+}
+
 fn extract_expr_from_jsx_expr(expr: JSXExpr) -> Box<Expr> {
     match expr {
         JSXExpr::Expr(expr) => expr,
@@ -191,10 +844,10 @@ fn extract_expr_from_jsx_expr(expr: JSXExpr) -> Box<Expr> {
     }
 }
 
-fn create_expr_expr_tpl(left_expr: &Box<Expr>, right_expr: &Box<Expr>) -> Box<Expr> {
+fn create_expr_expr_tpl(left_expr: &Expr, right_expr: &Expr) -> Box<Expr> {
     Box::new(Expr::Tpl(Tpl {
         span: DUMMY_SP,
-        exprs: vec![left_expr.clone(), right_expr.clone()],
+        exprs: vec![Box::new(left_expr.clone()), Box::new(right_expr.clone())],
         quasis: vec![
             TplElement {
                 raw: "".into(),
@@ -218,11 +871,11 @@ fn create_expr_expr_tpl(left_expr: &Box<Expr>, right_expr: &Box<Expr>) -> Box<Ex
     }))
 }
 
-fn create_lit_expr_tpl(left_expr: &str, right_expr: &Box<Expr>) -> Box<Expr> {
+fn create_lit_expr_tpl(left_expr: &str, right_expr: &Expr) -> Box<Expr> {
     let left_padded_expr = format!("{} ", left_expr);
     Box::new(Expr::Tpl(Tpl {
         span: DUMMY_SP,
-        exprs: vec![right_expr.clone()],
+        exprs: vec![Box::new(right_expr.clone())],
         quasis: vec![
             TplElement {
                 raw: left_padded_expr.clone().into(),
@@ -242,6 +895,7 @@ fn create_lit_expr_tpl(left_expr: &str, right_expr: &Box<Expr>) -> Box<Expr> {
 
 fn update_element_class_names(
     n: &mut JSXOpeningElement,
+    target: &str,
     class_names_opt: Option<JSXAttr>,
     generated_name: String,
 ) {
@@ -278,7 +932,7 @@ fn update_element_class_names(
             span: DUMMY_SP,
             name: JSXAttrName::Ident(Ident {
                 span: DUMMY_SP,
-                sym: "className".into(),
+                sym: target.into(),
                 optional: false,
             }),
             value: Some(JSXAttrValue::Lit(Lit::Str(Str {
@@ -292,8 +946,9 @@ fn update_element_class_names(
 
 fn update_element_class_names_with_expr(
     n: &mut JSXOpeningElement,
+    target: &str,
     class_names_opt: Option<JSXAttr>,
-    style_name_expr: &Box<Expr>,
+    style_name_expr: &Expr,
 ) {
     // getClassName(<style_name_expr>, _styleNameObjMap)
     let runtime_expr = Box::new(Expr::Call(CallExpr {
@@ -306,7 +961,7 @@ fn update_element_class_names_with_expr(
         args: vec![
             ExprOrSpread {
                 spread: None,
-                expr: style_name_expr.clone(),
+                expr: Box::new(style_name_expr.clone()),
             },
             ExprOrSpread {
                 spread: None,
@@ -330,7 +985,7 @@ fn update_element_class_names_with_expr(
                 }
                 // className="literal"
                 Some(JSXAttrValue::Lit(Lit::Str(str_lit_val))) => {
-                    create_lit_expr_tpl(&str_lit_val.value.to_string(), &runtime_expr)
+                    create_lit_expr_tpl(&str_lit_val.value, &runtime_expr)
                 }
                 _ => "".into(),
             };
@@ -347,7 +1002,7 @@ fn update_element_class_names_with_expr(
                 span: DUMMY_SP,
                 name: JSXAttrName::Ident(Ident {
                     span: DUMMY_SP,
-                    sym: "className".into(),
+                    sym: target.into(),
                     optional: false,
                 }),
                 value: Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
@@ -380,9 +1035,14 @@ fn create_helper_import_decl() -> ModuleItem {
 
 fn create_style_map_decl(style_name_map: &HashMap<JsWord, HashMap<String, String>>) -> ModuleItem {
     let mut props = Vec::new();
-    for (import, style_name_map) in style_name_map.iter() {
+    // sort keys so that the emitted map does not depend on HashMap iteration order
+    let mut imports: Vec<_> = style_name_map.iter().collect();
+    imports.sort_by_key(|(import, _)| *import);
+    for (import, style_name_map) in imports {
         let mut nested_props = Vec::new();
-        for (key, value) in style_name_map.iter() {
+        let mut entries: Vec<_> = style_name_map.iter().collect();
+        entries.sort();
+        for (key, value) in entries {
             nested_props.push(PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
                 key: PropName::Str(key.clone().into()),
                 value: value.clone().into(),
@@ -428,104 +1088,92 @@ impl VisitMut for AutoMapCssModules {
     // https://rustdoc.swc.rs/swc_ecma_visit/trait.VisitMut.html
 
     fn visit_mut_jsx_opening_element(&mut self, n: &mut JSXOpeningElement) {
-        let mut class_names: Option<JSXAttr> = None;
-        let mut style_names: Option<JSXAttr> = None;
-
-        for attr in n.attrs.iter() {
-            match attr {
-                JSXAttrOrSpread::JSXAttr(jsx_attr) => {
-                    if let JSXAttrName::Ident(Ident { sym, .. }) = &jsx_attr.name {
-                        if sym == "styleName" {
-                            style_names = Some(jsx_attr.clone());
-                        } else if sym == "className" {
-                            class_names = Some(jsx_attr.clone());
-                        }
-                    }
-                }
-                JSXAttrOrSpread::SpreadElement(_) => {}
-            }
-        }
+        // expands the macros used in attributes first
+        n.visit_mut_children_with(self);
 
-        if style_names.is_none() {
-            return;
+        let (source, target) = STYLE_NAME_ATTRIBUTE;
+        self.map_attribute(n, source, target);
+        for (source, target) in self.config.attribute_names.clone() {
+            self.map_attribute(n, &source, &target);
         }
+        self.map_style_vars(n);
+    }
 
-        // delete className and styleName attributes, as they will be replaced
-        n.attrs.retain(|attr| match attr {
-            JSXAttrOrSpread::JSXAttr(jsx_attr) => {
-                if let JSXAttrName::Ident(Ident { sym, .. }) = &jsx_attr.name {
-                    sym != "className" && sym != "styleName"
-                } else {
-                    true
-                }
-            }
-            JSXAttrOrSpread::SpreadElement(_) => true,
-        });
-
-        if let Some(style_names) = style_names {
-            match &style_names.value {
-                // styleName="style1 foo.style2"
-                Some(JSXAttrValue::Lit(Lit::Str(str_lit_val))) => {
-                    let mut generated_names = Vec::new();
-                    for style_name in str_lit_val.value.split_whitespace() {
-                        generated_names.push(
-                            self.get_generated_name(style_name, &style_names.span)
-                                .to_string(),
-                        );
+    fn visit_mut_expr(&mut self, n: &mut Expr) {
+        if let Expr::Call(call) = n {
+            if let Some(macro_name) = self.macro_name_of(call) {
+                call.args.visit_mut_with(self);
+                match self.expand_macro_call(&macro_name, call) {
+                    Some(expanded) => *n = expanded,
+                    None => {
+                        self.kept_macro_names.insert(macro_name);
                     }
-                    update_element_class_names(n, class_names, generated_names.join(" "));
                 }
-                // styleName={style3}
-                Some(JSXAttrValue::JSXExprContainer(JSXExprContainer { expr, .. })) => match expr {
-                    JSXExpr::Expr(expr) => {
-                        self.is_runtime_helper_req = true;
-                        update_element_class_names_with_expr(n, class_names, expr);
-                    }
-                    _ => (),
-                },
-                _ => (),
+                return;
             }
         }
 
         n.visit_mut_children_with(self);
+        // the macro is passed around, e.g. `const scoped = scopedName`
+        if let Expr::Ident(ident) = n {
+            if self.macro_names.contains(&ident.sym) {
+                self.kept_macro_names.insert(ident.sym.clone());
+            }
+        }
     }
 
     fn visit_mut_import_decl(&mut self, n: &mut ImportDecl) {
         n.visit_mut_children_with(self);
-        if !n
-            .src
-            .value
-            .ends_with(self.config.css_modules_suffix.as_str())
-        {
+        if n.src.value == MACRO_SRC {
+            for specifier in n.specifiers.iter() {
+                if let ImportSpecifier::Named(named) = specifier {
+                    let imported = match &named.imported {
+                        Some(ModuleExportName::Ident(ident)) => &ident.sym,
+                        Some(ModuleExportName::Str(str_lit)) => &str_lit.value,
+                        None => &named.local.sym,
+                    };
+                    if imported == "scopedName" {
+                        self.macro_names.push(named.local.sym.clone());
+                    }
+                }
+            }
+            return;
+        }
+        let (path, _, _) = parse_resource(&n.src.value);
+        if !path.ends_with(self.config.css_modules_suffix.as_str()) {
             return;
         }
 
         let src = &n.src.value;
 
         if n.specifiers.is_empty() {
-            self.add_import(&JsWord::from(""), src);
+            self.add_import(&JsWord::from(""), src, n.span);
             return;
         }
 
         for specifier in n.specifiers.iter() {
             match specifier {
                 ImportSpecifier::Default(default) => {
-                    self.add_import(&default.local.sym, src);
+                    self.add_import(&default.local.sym, src, n.span);
                 }
 
-                ImportSpecifier::Namespace(namespace) => self.add_import(&namespace.local.sym, src),
+                ImportSpecifier::Namespace(namespace) => {
+                    self.add_import(&namespace.local.sym, src, n.span)
+                }
 
-                ImportSpecifier::Named(_) => HANDLER.with(|handler| {
-                    handler
-                        .struct_span_err(n.span, "Named imports are not supported")
-                        .emit();
-                }),
+                ImportSpecifier::Named(_) => self.report(
+                    n.span,
+                    &Error::NamedImport {
+                        src: src.to_string(),
+                    },
+                ),
             }
         }
     }
 
     fn visit_mut_module(&mut self, n: &mut Module) {
         n.visit_mut_children_with(self);
+        self.remove_expanded_macro_imports(n);
         if !self.is_runtime_helper_req {
             return;
         }
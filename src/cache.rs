@@ -0,0 +1,375 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::{CssModulesMode, CssParserConfig, ScopedNaming},
+    css_exports::ExportKinds,
+    error::CssLocation,
+    process_stylesheet::ParsedStylesheet,
+    Error,
+};
+
+/// Class maps of the stylesheets parsed by this process,
+/// shared by every file and every thread swc transforms with
+static STYLE_MAPS: OnceLock<Mutex<HashMap<CacheKey, CacheEntry>>> = OnceLock::new();
+
+/// Identifies a stylesheet along with the settings its class names depend on
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub fs_path: PathBuf,
+    pub full_path: PathBuf,
+    pub naming: ScopedNaming,
+    pub mode: CssModulesMode,
+    pub parser: CssParserConfig,
+    /// `Config::alias`, used to resolve the stylesheets imported and composed from
+    pub alias: BTreeMap<String, String>,
+}
+
+/// State of a file on disk, used to detect changes between lookups
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fingerprint {
+    Metadata {
+        modified: SystemTime,
+        len: u64,
+    },
+    /// used when the filesystem does not report modification times
+    Content(u64),
+}
+
+impl Fingerprint {
+    /// Returns `None` if the file can not be read
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        match metadata.modified() {
+            Ok(modified) => Some(Fingerprint::Metadata {
+                modified,
+                len: metadata.len(),
+            }),
+            Err(_) => {
+                let mut hasher = DefaultHasher::new();
+                fs::read(path).ok()?.hash(&mut hasher);
+                Some(Fingerprint::Content(hasher.finish()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    fingerprint: Fingerprint,
+    /// other files read to build the map, e.g. the targets of `composes`
+    dependencies: Vec<(PathBuf, Option<Fingerprint>)>,
+    parsed: ParsedStylesheet,
+}
+
+impl CacheEntry {
+    fn is_fresh(&self, fingerprint: &Fingerprint) -> bool {
+        self.fingerprint == *fingerprint
+            && self
+                .dependencies
+                .iter()
+                .all(|(path, dependency)| Fingerprint::of(path) == *dependency)
+    }
+}
+
+fn style_maps() -> &'static Mutex<HashMap<CacheKey, CacheEntry>> {
+    STYLE_MAPS.get_or_init(Default::default)
+}
+
+/// Returns the cached stylesheet of `key` if neither it nor its dependencies changed
+pub fn get(key: &CacheKey, fingerprint: &Fingerprint) -> Option<ParsedStylesheet> {
+    // a panic while holding the lock can not leave an entry half written
+    let entries = style_maps().lock().unwrap_or_else(|err| err.into_inner());
+    entries
+        .get(key)
+        .filter(|entry| entry.is_fresh(fingerprint))
+        .map(|entry| entry.parsed.clone())
+}
+
+/// `fingerprint` must be taken before the stylesheet was read,
+/// so that a change made while parsing is picked up by the next lookup
+pub fn insert(key: CacheKey, fingerprint: Fingerprint, parsed: ParsedStylesheet) {
+    let dependencies = parsed
+        .dependencies
+        .iter()
+        .map(|path| (path.clone(), Fingerprint::of(path)))
+        .collect();
+
+    style_maps()
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(
+            key,
+            CacheEntry {
+                fingerprint,
+                dependencies,
+                parsed,
+            },
+        );
+}
+
+/// Bumped whenever the format of the entries or the generated names change
+const DISK_CACHE_VERSION: u32 = 10;
+
+/// Class maps stored on disk by previous builds, in `Config::cache_dir`
+///
+/// Entries are named after a hash of the stylesheet contents, its path and the naming settings,
+/// so a changed stylesheet never hits a stale entry. The cache is best effort: unreadable,
+/// corrupt or outdated entries are ignored and replaced, failed writes are ignored.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    /// directory that can actually be read and written, works with virtualized fs
+    dir: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DiskCacheEntry {
+    version: u32,
+    full_path: PathBuf,
+    /// content hashes of the other files read to build the map, `None` if missing
+    dependencies: Vec<(PathBuf, Option<String>)>,
+    style_name_map: HashMap<String, String>,
+    kinds: ExportKinds,
+    /// message and location of the css errors lightningcss recovered from
+    warnings: Vec<(String, Option<CssLocation>)>,
+}
+
+fn content_hash(contents: &[u8]) -> String {
+    base16ct::lower::encode_string(&Sha256::digest(contents))
+}
+
+fn file_hash(path: &Path) -> Option<String> {
+    fs::read(path).ok().map(|contents| content_hash(&contents))
+}
+
+impl DiskCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn entry_path(&self, key: &CacheKey, contents: &str) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(DISK_CACHE_VERSION.to_le_bytes());
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update(contents);
+        hasher.update(key.full_path.to_string_lossy().as_bytes());
+        hasher.update(format!("{:?}", key.naming));
+        hasher.update(format!("{:?}", key.mode));
+        hasher.update(format!("{:?}", key.parser));
+        hasher.update(format!("{:?}", key.alias));
+        self.dir.join(format!(
+            "{}.json",
+            base16ct::lower::encode_string(&hasher.finalize())
+        ))
+    }
+
+    /// Returns the stored stylesheet with `key` and `contents`
+    pub fn load(&self, key: &CacheKey, contents: &str) -> Option<ParsedStylesheet> {
+        let entry = fs::read(self.entry_path(key, contents)).ok()?;
+        let entry: DiskCacheEntry = serde_json::from_slice(&entry).ok()?;
+
+        let is_fresh = entry.version == DISK_CACHE_VERSION
+            && entry.full_path == key.full_path
+            && entry
+                .dependencies
+                .iter()
+                .all(|(path, hash)| file_hash(path) == *hash);
+        is_fresh.then(|| {
+            let warnings =
+                entry
+                    .warnings
+                    .into_iter()
+                    .map(|(message, location)| Error::RecoveredStylesheet {
+                        path: key.full_path.clone(),
+                        message,
+                        location,
+                    });
+            let dependencies = entry.dependencies.into_iter().map(|(path, _)| path);
+            ParsedStylesheet {
+                style_name_map: entry.style_name_map,
+                diagnostics: warnings.collect(),
+                dependencies: dependencies.collect(),
+                kinds: entry.kinds,
+            }
+        })
+    }
+
+    /// Only stylesheets whose diagnostics are the css recovered from in the stylesheet itself are stored,
+    /// other problems, e.g. in the stylesheets composed from, are found again by the next build
+    pub fn store(&self, key: &CacheKey, contents: &str, parsed: &ParsedStylesheet) {
+        let mut warnings = Vec::new();
+        for diagnostic in &parsed.diagnostics {
+            match diagnostic {
+                Error::RecoveredStylesheet {
+                    path,
+                    message,
+                    location,
+                } if *path == key.full_path => warnings.push((message.clone(), location.clone())),
+                _ => return,
+            }
+        }
+
+        let entry = DiskCacheEntry {
+            version: DISK_CACHE_VERSION,
+            full_path: key.full_path.clone(),
+            dependencies: parsed
+                .dependencies
+                .iter()
+                .map(|path| (path.clone(), file_hash(path)))
+                .collect(),
+            style_name_map: parsed.style_name_map.clone(),
+            kinds: parsed.kinds.clone(),
+            warnings,
+        };
+        let Ok(entry) = serde_json::to_vec(&entry) else {
+            return;
+        };
+
+        // write to a temporary file first so that concurrent builds never read a partial entry
+        let path = self.entry_path(key, contents);
+        let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        let written = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&temp_path, entry))
+            .and_then(|_| fs::rename(&temp_path, &path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic_names::{LocalIdentHashOptions, NamingPreset};
+
+    fn key(fs_path: &Path, generate_scoped_name: &str) -> CacheKey {
+        CacheKey {
+            fs_path: fs_path.to_path_buf(),
+            full_path: fs_path.to_path_buf(),
+            naming: ScopedNaming {
+                naming_preset: NamingPreset::GenericNames,
+                generate_scoped_name: generate_scoped_name.to_string(),
+                hash_prefix: String::new(),
+                context: PathBuf::from("/"),
+                local_ident_hash: LocalIdentHashOptions::default(),
+                reg_exp: None,
+                resource_query: String::new(),
+                resource_fragment: String::new(),
+            },
+            mode: CssModulesMode::Local,
+            parser: CssParserConfig::default(),
+            alias: BTreeMap::new(),
+        }
+    }
+
+    fn parsed(local: &str, generated: &str) -> ParsedStylesheet {
+        ParsedStylesheet {
+            style_name_map: HashMap::from([(local.to_string(), generated.to_string())]),
+            diagnostics: Vec::new(),
+            dependencies: Vec::new(),
+            kinds: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn invalidates_changed_files() {
+        let dir = std::env::temp_dir().join(format!("style-map-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let stylesheet = dir.join("styles.css");
+        let dependency = dir.join("base.css");
+        fs::write(&stylesheet, ".a {}").unwrap();
+        fs::write(&dependency, ".b {}").unwrap();
+
+        let mut map = parsed("a", "a_1");
+        map.dependencies.push(dependency.clone());
+        let fingerprint = Fingerprint::of(&stylesheet).unwrap();
+        insert(
+            key(&stylesheet, "[local]_1"),
+            fingerprint.clone(),
+            map.clone(),
+        );
+
+        assert_eq!(
+            get(&key(&stylesheet, "[local]_1"), &fingerprint),
+            Some(map.clone())
+        );
+        // naming options are part of the key
+        assert_eq!(get(&key(&stylesheet, "[local]_2"), &fingerprint), None);
+
+        fs::write(&dependency, ".b {} .c {}").unwrap();
+        assert_eq!(get(&key(&stylesheet, "[local]_1"), &fingerprint), None);
+
+        // fresh again once stored with the changed dependency
+        insert(
+            key(&stylesheet, "[local]_1"),
+            fingerprint.clone(),
+            map.clone(),
+        );
+        assert_eq!(get(&key(&stylesheet, "[local]_1"), &fingerprint), Some(map));
+
+        fs::write(&stylesheet, ".a {} .d {}").unwrap();
+        let changed = Fingerprint::of(&stylesheet).unwrap();
+        assert_ne!(changed, fingerprint);
+        assert_eq!(get(&key(&stylesheet, "[local]_1"), &changed), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn disk_cache_ignores_stale_and_corrupt_entries() {
+        let dir = std::env::temp_dir().join(format!("disk-cache-{}", std::process::id()));
+        let cache = DiskCache::new(dir.join("cache"));
+        let stylesheet = dir.join("styles.css");
+        let mut map = parsed("a", "a_1");
+        map.diagnostics.push(Error::RecoveredStylesheet {
+            path: stylesheet.clone(),
+            message: "Unexpected token".to_string(),
+            location: Some(CssLocation::new(".a {}", 0, 1)),
+        });
+
+        assert_eq!(cache.load(&key(&stylesheet, "[local]_1"), ".a {}"), None);
+        cache.store(&key(&stylesheet, "[local]_1"), ".a {}", &map);
+        assert_eq!(
+            cache.load(&key(&stylesheet, "[local]_1"), ".a {}"),
+            Some(map.clone())
+        );
+        assert_eq!(cache.load(&key(&stylesheet, "[local]_1"), ".a {} "), None);
+        assert_eq!(cache.load(&key(&stylesheet, "[local]_2"), ".a {}"), None);
+
+        let entry_path = cache.entry_path(&key(&stylesheet, "[local]_1"), ".a {}");
+        fs::write(&entry_path, "{\"version\": 1, \"full_pa").unwrap();
+        assert_eq!(cache.load(&key(&stylesheet, "[local]_1"), ".a {}"), None);
+
+        let dependency = dir.join("base.css");
+        fs::write(&dependency, ".b {}").unwrap();
+        map.dependencies.push(dependency.clone());
+        cache.store(&key(&stylesheet, "[local]_1"), ".a {}", &map);
+        assert_eq!(
+            cache.load(&key(&stylesheet, "[local]_1"), ".a {}"),
+            Some(map.clone())
+        );
+        fs::write(&dependency, ".c {}").unwrap();
+        assert_eq!(cache.load(&key(&stylesheet, "[local]_1"), ".a {}"), None);
+
+        // problems outside of the stylesheet are not stored
+        map.diagnostics.push(Error::ReadStylesheet {
+            path: dir.join("missing.css"),
+        });
+        cache.store(&key(&stylesheet, "[local]_1"), ".a {} .e {}", &map);
+        assert_eq!(
+            cache.load(&key(&stylesheet, "[local]_1"), ".a {} .e {}"),
+            None
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,19 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use path_absolutize::Absolutize;
 use serde::Deserialize;
 use serde_inline_default::serde_inline_default;
 use ts_rs::TS;
 
+use crate::{
+    diagnostics::{DiagnosticCode, Severity},
+    generic_names::{LocalIdentHashOptions, NamingPreset},
+    glob::Glob,
+};
+
 // TODO: locals_convention
 
 #[serde_inline_default]
@@ -17,6 +29,423 @@ pub struct Config {
     #[serde_inline_default(".css".to_string())]
     pub css_modules_suffix: String,
 
+    /// where stylesheets are read from, defaults to cwd
     #[serde_inline_default("".to_string())]
     pub root: String,
-}
\ No newline at end of file
+
+    /// context used to generate hashes, like css-loader's `localIdentContext`,
+    /// relative paths are resolved from `root`, defaults to `root`
+    #[serde_inline_default("".to_string())]
+    pub local_ident_context: String,
+
+    /// algorithm used to generate scoped names, defaults to generic-names
+    #[serde(default)]
+    pub naming_preset: NamingPreset,
+
+    /// css-loader `localIdentHashSalt`, only used by the css-loader preset
+    #[serde_inline_default("".to_string())]
+    pub local_ident_hash_salt: String,
+
+    /// css-loader `localIdentHashFunction`, only used by the css-loader preset
+    #[serde_inline_default("md4".to_string())]
+    pub local_ident_hash_function: String,
+
+    /// css-loader `localIdentHashDigest`, only used by the css-loader preset
+    #[serde_inline_default("hex".to_string())]
+    pub local_ident_hash_digest: String,
+
+    /// css-loader `localIdentHashDigestLength`, only used by the css-loader preset
+    #[serde_inline_default(20)]
+    pub local_ident_hash_digest_length: usize,
+
+    /// css-loader `localIdentRegExp`, used by the generic-names and css-loader presets,
+    /// `[N]` in the pattern is replaced by the N-th capture group of the stylesheet path
+    #[serde_inline_default("".to_string())]
+    pub local_ident_reg_exp: String,
+
+    /// naming settings for stylesheets matching a glob, first match wins
+    #[serde_inline_default(Vec::new())]
+    pub overrides: Vec<NamingOverride>,
+
+    /// css-loader `modules.mode` of stylesheets matching a glob, first match wins,
+    /// `local` for the others
+    #[serde(default)]
+    pub modes: Vec<ModeOverride>,
+
+    /// severity of each diagnostic, see `DiagnosticCode::default_severity` for the rest
+    #[serde_inline_default(HashMap::new())]
+    pub diagnostics: HashMap<DiagnosticCode, Severity>,
+
+    /// directory where class maps are stored between builds, relative to `root`,
+    /// disabled when empty
+    #[serde_inline_default("".to_string())]
+    pub cache_dir: String,
+
+    /// lightningcss parser settings
+    #[serde(default)]
+    pub css_parser: CssParserConfig,
+
+    /// whether a styleName may be resolved at runtime by the injected helper
+    #[serde(default)]
+    pub runtime_fallback: RuntimeFallback,
+
+    /// like webpack's `resolve.alias`, maps a specifier prefix used by `@import` and `composes`
+    /// to a path relative to `root`
+    #[serde_inline_default(BTreeMap::new())]
+    pub alias: BTreeMap<String, String>,
+
+    /// attributes resolved like `styleName`, mapped to the attribute receiving the scoped names,
+    /// replaces the default `idName` to `id` mapping when set
+    #[serde_inline_default(BTreeMap::from([("idName".to_string(), "id".to_string())]))]
+    pub attribute_names: BTreeMap<String, String>,
+
+    /// reads the class maps from a manifest written by the css build instead of
+    /// compiling the stylesheets
+    #[serde(default)]
+    #[ts(optional)]
+    pub class_map_source: Option<ClassMapSource>,
+}
+
+/// Where the class maps of the imported stylesheets come from
+#[derive(Debug, Deserialize, Clone, TS)]
+#[ts(export, export_to = "types.d.ts")]
+pub struct ClassMapSource {
+    /// json file relative to `root`, mapping the stylesheet paths relative to `root`
+    /// to their class maps, e.g. `{ "src/a.module.css": { "btn": "a_btn_x1" } }`
+    pub manifest: String,
+    /// compiles the stylesheets as well and reports the names differing from the manifest
+    #[serde(default)]
+    pub verify: bool,
+}
+
+/// Syntax accepted when parsing stylesheets, maps to lightningcss `ParserOptions`
+#[derive(Debug, Default, Deserialize, Clone, PartialEq, Eq, Hash, TS)]
+#[serde(default)]
+#[ts(export, export_to = "types.d.ts")]
+pub struct CssParserConfig {
+    /// parse nested rules, `ParserFlags::NESTING`
+    pub nesting: bool,
+    /// parse `@custom-media` rules, `ParserFlags::CUSTOM_MEDIA`
+    pub custom_media: bool,
+    /// skip invalid rules and declarations instead of failing, they are reported as warnings
+    pub error_recovery: bool,
+    /// scope custom properties and other dashed idents per stylesheet,
+    /// lightningcss css modules `dashed_idents`
+    pub dashed_idents: bool,
+}
+
+/// What to do with a styleName that can not be resolved at compile time
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export, export_to = "types.d.ts")]
+pub enum RuntimeFallback {
+    /// inject the runtime helper
+    #[default]
+    Allow,
+    /// inject the runtime helper and report a warning
+    Warn,
+    /// report an error, the runtime helper is never injected
+    Deny,
+}
+
+/// Overrides the naming settings for stylesheets matching `test`
+#[derive(Debug, Deserialize, Clone, TS)]
+#[ts(export, export_to = "types.d.ts")]
+pub struct NamingOverride {
+    /// glob matched against the stylesheet path relative to `root`,
+    /// or against the absolute path if the glob itself is absolute
+    #[ts(type = "string")]
+    pub test: Glob,
+
+    #[ts(optional)]
+    pub naming_preset: Option<NamingPreset>,
+
+    #[ts(optional)]
+    pub generate_scoped_name: Option<String>,
+
+    #[ts(optional)]
+    pub hash_prefix: Option<String>,
+
+    /// context used to generate hashes, relative to `root`
+    #[ts(optional)]
+    pub context: Option<String>,
+}
+
+/// How the names of a stylesheet are scoped, like css-loader's `modules.mode`
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq, Hash, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export, export_to = "types.d.ts")]
+pub enum CssModulesMode {
+    /// names are scoped unless wrapped in `:global`
+    #[default]
+    Local,
+    /// names are used as is
+    Global,
+    /// same as `local`, every selector has to contain a local class or id
+    Pure,
+    /// only the ICSS `:import` and `:export` blocks are handled, nothing is scoped
+    Icss,
+}
+
+/// Sets the mode of stylesheets matching `test`
+#[derive(Debug, Deserialize, Clone, TS)]
+#[ts(export, export_to = "types.d.ts")]
+pub struct ModeOverride {
+    /// glob matched like `NamingOverride::test`
+    #[ts(type = "string")]
+    pub test: Glob,
+    pub mode: CssModulesMode,
+}
+
+/// Naming settings resolved for a single stylesheet
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScopedNaming {
+    pub naming_preset: NamingPreset,
+    pub generate_scoped_name: String,
+    pub hash_prefix: String,
+    pub context: PathBuf,
+    pub local_ident_hash: LocalIdentHashOptions,
+    pub reg_exp: Option<String>,
+    /// `?query` and `#fragment` of the import, empty for stylesheets imported by other ones
+    pub resource_query: String,
+    pub resource_fragment: String,
+}
+
+impl Config {
+    pub fn severity_of(&self, code: DiagnosticCode) -> Severity {
+        match (self.diagnostics.get(&code), code) {
+            (Some(severity), _) => *severity,
+            (None, DiagnosticCode::RuntimeFallback) => match self.runtime_fallback {
+                RuntimeFallback::Allow => Severity::Off,
+                RuntimeFallback::Warn => Severity::Warn,
+                RuntimeFallback::Deny => Severity::Error,
+            },
+            (None, _) => code.default_severity(),
+        }
+    }
+
+    /// Absolute path of `cache_dir`, if set
+    pub fn cache_dir_in(&self, root: &Path) -> Option<PathBuf> {
+        (!self.cache_dir.is_empty()).then(|| resolve_from(root, &self.cache_dir))
+    }
+
+    /// Absolute path of the `class_map_source` manifest, if set
+    pub fn manifest_in(&self, root: &Path) -> Option<PathBuf> {
+        self.class_map_source
+            .as_ref()
+            .map(|source| resolve_from(root, &source.manifest))
+    }
+
+    /// Absolute path `specifier` points to if it starts with an alias, the longest alias wins
+    pub fn resolve_alias(&self, root: &Path, specifier: &str) -> Option<PathBuf> {
+        self.alias
+            .iter()
+            .filter_map(|(alias, target)| {
+                let rest = specifier.strip_prefix(alias.as_str())?;
+                match rest.strip_prefix('/') {
+                    Some(rest) => Some((alias, resolve_from(&resolve_from(root, target), rest))),
+                    None if rest.is_empty() => Some((alias, resolve_from(root, target))),
+                    None => None,
+                }
+            })
+            .max_by_key(|(alias, _)| alias.len())
+            .map(|(_, path)| path)
+    }
+
+    /// Returns the naming settings of the first override matching `file_path`,
+    /// falling back to the top level settings.
+    pub fn scoped_naming_for(&self, root: &Path, file_path: &Path) -> ScopedNaming {
+        let context = if self.local_ident_context.is_empty() {
+            root.to_path_buf()
+        } else {
+            resolve_from(root, &self.local_ident_context)
+        };
+
+        let mut naming = ScopedNaming {
+            naming_preset: self.naming_preset,
+            generate_scoped_name: self.generate_scoped_name.clone(),
+            hash_prefix: self.hash_prefix.clone(),
+            context,
+            local_ident_hash: LocalIdentHashOptions {
+                salt: self.local_ident_hash_salt.clone(),
+                function: self.local_ident_hash_function.clone(),
+                digest: self.local_ident_hash_digest.clone(),
+                digest_length: self.local_ident_hash_digest_length,
+            },
+            reg_exp: (!self.local_ident_reg_exp.is_empty())
+                .then(|| self.local_ident_reg_exp.clone()),
+            resource_query: "".to_string(),
+            resource_fragment: "".to_string(),
+        };
+
+        let matching = self
+            .overrides
+            .iter()
+            .find(|o| is_match(&o.test, root, file_path));
+
+        if let Some(matching) = matching {
+            if let Some(naming_preset) = matching.naming_preset {
+                naming.naming_preset = naming_preset;
+            }
+            if let Some(generate_scoped_name) = &matching.generate_scoped_name {
+                naming.generate_scoped_name = generate_scoped_name.clone();
+            }
+            if let Some(hash_prefix) = &matching.hash_prefix {
+                naming.hash_prefix = hash_prefix.clone();
+            }
+            if let Some(context) = &matching.context {
+                naming.context = resolve_from(root, context);
+            }
+        }
+
+        naming
+    }
+
+    /// Returns the mode of the first mode override matching `file_path`
+    pub fn mode_for(&self, root: &Path, file_path: &Path) -> CssModulesMode {
+        self.modes
+            .iter()
+            .find(|o| is_match(&o.test, root, file_path))
+            .map_or(CssModulesMode::Local, |o| o.mode)
+    }
+}
+
+/// Matches `test` against `file_path` relative to `root`,
+/// or against the absolute path if the glob itself is absolute
+fn is_match(test: &Glob, root: &Path, file_path: &Path) -> bool {
+    let absolute_path = file_path.to_string_lossy().replace('\\', "/");
+    if test.to_string().starts_with('/') {
+        return test.is_match(&absolute_path);
+    }
+    let relative_path = pathdiff::diff_paths(file_path, root)
+        .map(|path| path.to_string_lossy().replace('\\', "/"))
+        .unwrap_or(absolute_path);
+    test.is_match(&relative_path)
+}
+
+fn resolve_from(root: &Path, path: &str) -> PathBuf {
+    Path::new(path)
+        .absolutize_from(root)
+        .map(|path| path.to_path_buf())
+        .unwrap_or_else(|_| root.join(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use serde_json::json;
+
+    use super::Config;
+    use crate::{
+        auto_map_css_module::virtual_path,
+        diagnostics::{DiagnosticCode, Severity},
+    };
+
+    fn config(value: serde_json::Value) -> Config {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn uses_configured_severities() {
+        let config = config(json!({
+            "diagnostics": {
+                "unresolved-style-name": "warn",
+                "ambiguous-style-name": "error",
+                "named-import": "off"
+            }
+        }));
+        assert_eq!(
+            config.severity_of(DiagnosticCode::UnresolvedStyleName),
+            Severity::Warn
+        );
+        assert_eq!(
+            config.severity_of(DiagnosticCode::AmbiguousStyleName),
+            Severity::Error
+        );
+        assert_eq!(
+            config.severity_of(DiagnosticCode::NamedImport),
+            Severity::Off
+        );
+        assert_eq!(
+            config.severity_of(DiagnosticCode::UnknownImportPrefix),
+            Severity::Error
+        );
+        assert_eq!(
+            config.severity_of(DiagnosticCode::CssRecoveredError),
+            Severity::Warn
+        );
+    }
+
+    #[test]
+    fn maps_runtime_fallback_to_severity() {
+        for (runtime_fallback, severity) in [
+            ("allow", Severity::Off),
+            ("warn", Severity::Warn),
+            ("deny", Severity::Error),
+        ] {
+            let config = config(json!({ "runtime_fallback": runtime_fallback }));
+            assert_eq!(
+                config.severity_of(DiagnosticCode::RuntimeFallback),
+                severity
+            );
+        }
+        assert_eq!(
+            config(json!({})).severity_of(DiagnosticCode::RuntimeFallback),
+            Severity::Off
+        );
+
+        // the diagnostics entry takes precedence
+        let config = config(json!({
+            "runtime_fallback": "deny",
+            "diagnostics": { "runtime-fallback": "warn" }
+        }));
+        assert_eq!(
+            config.severity_of(DiagnosticCode::RuntimeFallback),
+            Severity::Warn
+        );
+    }
+
+    #[test]
+    fn resolves_aliases() {
+        let config = config(json!({
+            "alias": {
+                "@ui": "src/ui",
+                "@ui/theme": "src/theme",
+                "@shared": "../shared"
+            }
+        }));
+        let root = Path::new("/project");
+        let resolve = |specifier| config.resolve_alias(root, specifier);
+
+        assert_eq!(
+            resolve("@ui/button.css"),
+            Some(PathBuf::from("/project/src/ui/button.css"))
+        );
+        // the longest alias wins
+        assert_eq!(
+            resolve("@ui/theme/colors.css"),
+            Some(PathBuf::from("/project/src/theme/colors.css"))
+        );
+        // an exact match is the target itself
+        assert_eq!(resolve("@ui"), Some(PathBuf::from("/project/src/ui")));
+        // aliases only match whole path segments
+        assert_eq!(resolve("@uikit/button.css"), None);
+        assert_eq!(resolve("./button.css"), None);
+    }
+
+    #[test]
+    fn resolves_aliases_outside_root() {
+        let config = config(json!({ "alias": { "@shared": "../shared" } }));
+        let root = Path::new("/project");
+
+        let full_path = config.resolve_alias(root, "@shared/a.css").unwrap();
+        assert_eq!(full_path, PathBuf::from("/shared/a.css"));
+        // the virtual fs of swc only exposes root
+        assert_eq!(virtual_path(root, &full_path), None);
+        assert_eq!(
+            virtual_path(root, Path::new("/project/src/a.css")),
+            Some(PathBuf::from("/cwd/src/a.css"))
+        );
+    }
+}
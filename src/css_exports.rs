@@ -0,0 +1,683 @@
+//! Collects the CSS modules exports of a stylesheet without printing it.
+//!
+//! lightningcss only computes exports while serializing the stylesheet, which costs as much
+//! as printing the whole file. The visitor below walks the same places the printer names
+//! (class and id selectors, custom idents such as keyframes names, dashed idents, `composes`) and gives up
+//! on the constructs it does not model, so that callers can fall back to the printer.
+//! It also records what each name is used for, which the printer does not tell.
+
+use std::{collections::HashMap, convert::Infallible};
+
+use lightningcss::{
+    css_modules::{CssModuleExport, CssModuleExports, CssModuleReference},
+    properties::{
+        animation::AnimationName,
+        css_modules::{Composes, Specifier},
+        custom::{EnvironmentVariable, EnvironmentVariableName, Variable},
+        Property,
+    },
+    rules::{keyframes::KeyframesName, CssRule, Location},
+    selector::{
+        Component, PseudoClass, PseudoElement, Selector, SelectorList, ViewTransitionPartName,
+    },
+    stylesheet::StyleSheet,
+    values::ident::{CustomIdent, DashedIdent, DashedIdentReference},
+    visit_types,
+    visitor::{Visit, VisitTypes, Visitor},
+};
+use serde::{Deserialize, Serialize};
+
+/// What an exported name is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportKind {
+    /// `.name`
+    Class,
+    /// `#name`
+    Id,
+    /// `@keyframes name`, or an animation name
+    Keyframes,
+    /// any other scoped identifier, e.g. a counter style or a container name
+    CustomIdent,
+    /// `--name`, a custom property or any other dashed ident, with `css_parser.dashed_idents`
+    DashedIdent,
+    /// `:global(.name)`, kept as is and not part of the exports
+    GlobalClass,
+    /// `@value name` or a key of `:export`, never found by the collector
+    Value,
+}
+
+impl ExportKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportKind::Class => "class",
+            ExportKind::Id => "id",
+            ExportKind::Keyframes => "keyframes",
+            ExportKind::CustomIdent => "custom-ident",
+            ExportKind::DashedIdent => "dashed-ident",
+            ExportKind::GlobalClass => "global-class",
+            ExportKind::Value => "value",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            ExportKind::Class,
+            ExportKind::Id,
+            ExportKind::Keyframes,
+            ExportKind::CustomIdent,
+            ExportKind::DashedIdent,
+            ExportKind::GlobalClass,
+            ExportKind::Value,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == name)
+    }
+}
+
+/// Kinds of each exported name, sorted, a name can be used as a class and an id at the same time
+pub type ExportKinds = HashMap<String, Vec<ExportKind>>;
+
+/// The stylesheet uses something only the printer knows how to name
+#[derive(Debug)]
+struct Unsupported;
+
+#[derive(Default)]
+struct ExportsCollector {
+    exports: CssModuleExports,
+    kinds: ExportKinds,
+    /// number of rules enclosing the current one
+    depth: usize,
+    /// whether dashed idents are named too, lightningcss `dashed_idents`
+    dashed_idents: bool,
+    /// skip what only the printer knows how to name instead of giving up,
+    /// only the kinds are then complete
+    lenient: bool,
+}
+
+/// Returns the exports lightningcss would report when printing `stylesheet`
+/// with the `[local]` pattern, or `None` if the printer has to be used instead.
+/// `dashed_idents` has to match the option the stylesheet was parsed with.
+///
+/// `is_referenced` is not tracked and always `false`.
+pub fn collect_exports(
+    stylesheet: &mut StyleSheet,
+    dashed_idents: bool,
+) -> Option<CssModuleExports> {
+    let mut collector = ExportsCollector {
+        dashed_idents,
+        ..ExportsCollector::default()
+    };
+    stylesheet.visit(&mut collector).ok()?;
+    Some(collector.exports)
+}
+
+/// Same as [`collect_exports`], along with the kinds of the names,
+/// which are also returned when the printer has to be used.
+pub fn collect_exports_with_kinds(
+    stylesheet: &mut StyleSheet,
+    dashed_idents: bool,
+) -> (Option<CssModuleExports>, ExportKinds) {
+    let mut collector = ExportsCollector {
+        dashed_idents,
+        ..ExportsCollector::default()
+    };
+    if stylesheet.visit(&mut collector).is_ok() {
+        return (Some(collector.exports), collector.kinds);
+    }
+
+    let mut collector = ExportsCollector {
+        dashed_idents,
+        lenient: true,
+        ..ExportsCollector::default()
+    };
+    // lenient collection never fails
+    let _ = stylesheet.visit(&mut collector);
+    (None, collector.kinds)
+}
+
+/// Keywords lightningcss keeps as strings instead of naming them
+fn is_css_wide_keyword(name: &str) -> bool {
+    [
+        "none",
+        "initial",
+        "inherit",
+        "unset",
+        "default",
+        "revert",
+        "revert-layer",
+    ]
+    .iter()
+    .any(|keyword| name.eq_ignore_ascii_case(keyword))
+}
+
+impl ExportsCollector {
+    fn add_local(&mut self, name: &str, kind: ExportKind) {
+        self.exports
+            .entry(name.to_string())
+            .or_insert_with(|| CssModuleExport {
+                name: name.to_string(),
+                composes: Vec::new(),
+                is_referenced: false,
+            });
+        self.add_kind(name, kind);
+    }
+
+    fn add_kind(&mut self, name: &str, kind: ExportKind) {
+        let kinds = self.kinds.entry(name.to_string()).or_default();
+        if let Err(index) = kinds.binary_search(&kind) {
+            kinds.insert(index, kind);
+        }
+    }
+
+    fn unsupported(&self) -> Result<(), Unsupported> {
+        if self.lenient {
+            Ok(())
+        } else {
+            Err(Unsupported)
+        }
+    }
+
+    fn add_selector(&mut self, selector: &Selector) -> Result<(), Unsupported> {
+        for component in selector.iter_raw_match_order() {
+            match component {
+                Component::Class(name) => self.add_local(&name.0, ExportKind::Class),
+                Component::ID(name) => self.add_local(&name.0, ExportKind::Id),
+                Component::Negation(selectors)
+                | Component::Where(selectors)
+                | Component::Is(selectors)
+                | Component::Any(_, selectors)
+                | Component::Has(selectors) => {
+                    for selector in selectors.iter() {
+                        self.add_selector(selector)?;
+                    }
+                }
+                // `:nth-child(An+B of S)` is printed verbatim
+                Component::Slotted(selector) | Component::Host(Some(selector)) => {
+                    self.add_selector(selector)?
+                }
+                Component::NonTSPseudoClass(PseudoClass::Local { selector }) => {
+                    self.add_selector(selector)?
+                }
+                // not renamed by the printer
+                Component::NonTSPseudoClass(PseudoClass::Global { selector }) => {
+                    self.add_global_selector(selector)
+                }
+                Component::PseudoElement(pseudo_element) => match pseudo_element {
+                    PseudoElement::CueFunction { selector }
+                    | PseudoElement::CueRegionFunction { selector } => {
+                        self.add_selector(selector)?
+                    }
+                    PseudoElement::ViewTransitionGroup { part_name }
+                    | PseudoElement::ViewTransitionImagePair { part_name }
+                    | PseudoElement::ViewTransitionOld { part_name }
+                    | PseudoElement::ViewTransitionNew { part_name } => {
+                        if let ViewTransitionPartName::Name(name) = part_name {
+                            self.add_local(&name.0, ExportKind::CustomIdent);
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the classes of `:global(selector)`, which are defined by the stylesheet unscoped
+    fn add_global_selector(&mut self, selector: &Selector) {
+        for component in selector.iter_raw_match_order() {
+            match component {
+                Component::Class(name) => self.add_kind(&name.0, ExportKind::GlobalClass),
+                Component::Negation(selectors)
+                | Component::Where(selectors)
+                | Component::Is(selectors)
+                | Component::Any(_, selectors)
+                | Component::Has(selectors) => {
+                    for selector in selectors.iter() {
+                        self.add_global_selector(selector);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Mirrors `CssModule::handle_composes`, the printer rejects the cases returning `Unsupported`
+    fn add_composes(
+        &mut self,
+        selectors: &SelectorList,
+        composes: &Composes,
+    ) -> Result<(), Unsupported> {
+        if self.depth > 0 {
+            return self.unsupported();
+        }
+
+        for selector in &selectors.0 {
+            let class = match selector.iter_raw_match_order().next() {
+                Some(Component::Class(class)) if selector.len() == 1 => class.0.to_string(),
+                _ => return self.unsupported(),
+            };
+            for name in &composes.names {
+                let reference = match &composes.from {
+                    None => CssModuleReference::Local {
+                        name: name.0.to_string(),
+                    },
+                    Some(Specifier::Global) => CssModuleReference::Global {
+                        name: name.0.to_string(),
+                    },
+                    Some(Specifier::File(file)) => CssModuleReference::Dependency {
+                        name: name.0.to_string(),
+                        specifier: file.to_string(),
+                    },
+                    // only produced by the bundler
+                    Some(Specifier::SourceIndex(_)) => return self.unsupported(),
+                };
+                let export = self.exports.get_mut(&class).ok_or(Unsupported)?;
+                if !export.composes.contains(&reference) {
+                    export.composes.push(reference);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `var(--name from "./other.css")` and `from global` are not exported
+    fn add_dashed_reference(&mut self, reference: &DashedIdentReference) {
+        if reference.from.is_none() {
+            self.add_local(&reference.ident.0, ExportKind::DashedIdent);
+        }
+    }
+
+    fn add_animation_name(&mut self, name: &AnimationName) {
+        match name {
+            AnimationName::Ident(name) => self.add_local(&name.0, ExportKind::Keyframes),
+            AnimationName::String(name) => self.add_local(name, ExportKind::Keyframes),
+            AnimationName::None => {}
+        }
+    }
+}
+
+impl<'i> Visitor<'i> for ExportsCollector {
+    type Error = Unsupported;
+
+    fn visit_types(&self) -> VisitTypes {
+        if self.dashed_idents {
+            visit_types!(
+                RULES
+                    | PROPERTIES
+                    | CUSTOM_IDENTS
+                    | DASHED_IDENTS
+                    | VARIABLES
+                    | ENVIRONMENT_VARIABLES
+            )
+        } else {
+            visit_types!(RULES | PROPERTIES | CUSTOM_IDENTS)
+        }
+    }
+
+    fn visit_rule(&mut self, rule: &mut CssRule<'i>) -> Result<(), Self::Error> {
+        match rule {
+            CssRule::Style(style) => {
+                for selector in &style.selectors.0 {
+                    self.add_selector(selector)?;
+                }
+                // composes needs the selectors of its rule
+                let declarations = style
+                    .declarations
+                    .declarations
+                    .iter()
+                    .chain(&style.declarations.important_declarations);
+                for property in declarations {
+                    if let Property::Composes(composes) = property {
+                        self.add_composes(&style.selectors, composes)?;
+                    }
+                }
+            }
+            CssRule::Keyframes(keyframes) => {
+                match &keyframes.name {
+                    KeyframesName::Ident(name) => self.add_local(&name.0, ExportKind::Keyframes),
+                    KeyframesName::Custom(name) if !is_css_wide_keyword(name) => {
+                        self.add_local(name, ExportKind::Keyframes)
+                    }
+                    KeyframesName::Custom(_) => {}
+                }
+                // only the declarations, the name is not an other custom ident
+                self.depth += 1;
+                let result = keyframes
+                    .keyframes
+                    .iter_mut()
+                    .try_for_each(|keyframe| keyframe.declarations.visit(self));
+                self.depth -= 1;
+                return result;
+            }
+            CssRule::Nesting(nesting) if self.lenient => {
+                for selector in &nesting.style.selectors.0 {
+                    self.add_selector(selector)?;
+                }
+            }
+            // selectors the visitor does not reach, or names only the printer resolves
+            CssRule::Nesting(_) | CssRule::Scope(_) | CssRule::Custom(_) => self.unsupported()?,
+            _ => {}
+        }
+
+        self.depth += 1;
+        let result = rule.visit_children(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn visit_property(&mut self, property: &mut Property<'i>) -> Result<(), Self::Error> {
+        match property {
+            // handled with the selectors of its rule, the names are not printed
+            Property::Composes(_) => return Ok(()),
+            // the names are the only custom idents of animations
+            Property::AnimationName(names, _) => {
+                for name in names.iter() {
+                    self.add_animation_name(name);
+                }
+                return Ok(());
+            }
+            Property::Animation(animations, _) => {
+                for animation in animations.iter() {
+                    self.add_animation_name(&animation.name);
+                }
+                return Ok(());
+            }
+            Property::FontPalette(name) => {
+                if self.dashed_idents {
+                    self.add_dashed_reference(name);
+                }
+                return Ok(());
+            }
+            // area names are written from strings
+            Property::GridTemplateAreas(_) | Property::GridTemplate(_) | Property::Grid(_) => {
+                self.unsupported()?
+            }
+            _ => {}
+        }
+        property.visit_children(self)
+    }
+
+    fn visit_custom_ident(&mut self, ident: &mut CustomIdent) -> Result<(), Self::Error> {
+        self.add_local(&ident.0, ExportKind::CustomIdent);
+        Ok(())
+    }
+
+    fn visit_dashed_ident(&mut self, ident: &mut DashedIdent) -> Result<(), Self::Error> {
+        self.add_local(&ident.0, ExportKind::DashedIdent);
+        Ok(())
+    }
+
+    fn visit_variable(&mut self, var: &mut Variable<'i>) -> Result<(), Self::Error> {
+        self.add_dashed_reference(&var.name);
+        match &mut var.fallback {
+            Some(fallback) => fallback.visit(self),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_environment_variable(
+        &mut self,
+        env: &mut EnvironmentVariable<'i>,
+    ) -> Result<(), Self::Error> {
+        if let EnvironmentVariableName::Custom(name) = &env.name {
+            self.add_dashed_reference(name);
+        }
+        match &mut env.fallback {
+            Some(fallback) => fallback.visit(self),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A selector of a style rule without a local class or id, which css-loader's pure mode rejects
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImpureSelector {
+    /// location of the style rule
+    pub loc: Location,
+    /// index of the selector in the selector list of the rule
+    pub index: usize,
+}
+
+/// Returns the selectors of `stylesheet` that contain no local class or id.
+/// Selectors of rules nested in a pure rule are pure, like `&:hover` or `span`.
+pub fn impure_selectors(stylesheet: &mut StyleSheet) -> Vec<ImpureSelector> {
+    let mut checker = PurityChecker::default();
+    let _ = stylesheet.visit(&mut checker);
+    checker.impure
+}
+
+#[derive(Default)]
+struct PurityChecker {
+    impure: Vec<ImpureSelector>,
+    /// whether each enclosing style rule is pure
+    parents: Vec<bool>,
+}
+
+/// Whether `selector` names a local class or id outside of `:global`
+fn has_local(selector: &Selector) -> bool {
+    selector
+        .iter_raw_match_order()
+        .any(|component| match component {
+            Component::Class(_) | Component::ID(_) => true,
+            Component::Negation(selectors)
+            | Component::Where(selectors)
+            | Component::Is(selectors)
+            | Component::Any(_, selectors)
+            | Component::Has(selectors) => selectors.iter().any(has_local),
+            Component::Slotted(selector) | Component::Host(Some(selector)) => has_local(selector),
+            Component::NonTSPseudoClass(PseudoClass::Local { selector }) => has_local(selector),
+            _ => false,
+        })
+}
+
+impl<'i> Visitor<'i> for PurityChecker {
+    type Error = Infallible;
+
+    fn visit_types(&self) -> VisitTypes {
+        visit_types!(RULES)
+    }
+
+    fn visit_rule(&mut self, rule: &mut CssRule<'i>) -> Result<(), Self::Error> {
+        match rule {
+            CssRule::Style(style) => {
+                let parent_is_pure = self.parents.last().copied().unwrap_or(false);
+                let mut is_pure = true;
+                for (index, selector) in style.selectors.0.iter().enumerate() {
+                    if !parent_is_pure && !has_local(selector) {
+                        is_pure = false;
+                        self.impure.push(ImpureSelector {
+                            loc: style.loc,
+                            index,
+                        });
+                    }
+                }
+                self.parents.push(parent_is_pure || is_pure);
+                let result = rule.visit_children(self);
+                self.parents.pop();
+                result
+            }
+            // keyframe selectors are percentages
+            CssRule::Keyframes(_) => Ok(()),
+            _ => rule.visit_children(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lightningcss::{
+        css_modules::{Config, Pattern},
+        printer::PrinterOptions,
+        stylesheet::{ParserFlags, ParserOptions},
+    };
+
+    use super::*;
+
+    fn parse(source: &str) -> StyleSheet<'_, '_> {
+        parse_with(source, false)
+    }
+
+    fn parse_with(source: &str, dashed_idents: bool) -> StyleSheet<'_, '_> {
+        StyleSheet::parse(
+            source,
+            ParserOptions {
+                filename: "styles.css".to_string(),
+                css_modules: Some(Config {
+                    pattern: Pattern::parse("[local]").unwrap(),
+                    dashed_idents,
+                }),
+                flags: ParserFlags::NESTING,
+                ..ParserOptions::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn printed_exports(source: &str, dashed_idents: bool) -> CssModuleExports {
+        let mut exports = parse_with(source, dashed_idents)
+            .to_css(PrinterOptions::default())
+            .unwrap()
+            .exports
+            .unwrap();
+        for export in exports.values_mut() {
+            export.is_referenced = false;
+        }
+        exports
+    }
+
+    #[test]
+    fn matches_printer() {
+        let source = r#"
+            .a, .b > .c:hover, #d { color: red; }
+            .e:not(.f):is(.g, .h):where(.i):has(> .j) {}
+            li:nth-child(2n of .k) {}
+            :host(.l) ::slotted(.m) {}
+            :global(.global) .n, :local(.o) :global(#global-id) {}
+            ::view-transition-old(p) {}
+            .q { composes: a b; composes: shared from "./shared.css"; composes: gl from global; }
+            .r { animation: s 1s, "t" 2s; animation-name: u, none; }
+            @keyframes s { from { opacity: 0; } }
+            @keyframes "v" { to { opacity: 1; } }
+            @media (min-width: 10px) { .w { list-style-type: x; } }
+            @supports (display: grid) { .y { counter-reset: z; } }
+            @layer base { .aa { grid-row: bb; } }
+            @container cc (min-width: 10px) { .dd { container-name: ee; } }
+            @counter-style ff { system: cyclic; symbols: "*"; }
+            .gg { & .hh { color: red; } .ii & { color: blue; } }
+        "#;
+
+        assert_eq!(
+            collect_exports(&mut parse(source), false),
+            Some(printed_exports(source, false))
+        );
+    }
+
+    #[test]
+    fn matches_printer_with_dashed_idents() {
+        let source = r#"
+            .a { --accent: red; color: var(--accent); background: var(--surface, var(--fallback)); }
+            .b { color: var(--shared from "./theme.css"); border-color: var(--gl from global); }
+            .c { margin: env(--gutter); font-palette: --palette; }
+            @font-palette-values --palette { font-family: Bixa; }
+        "#;
+
+        assert_eq!(
+            collect_exports(&mut parse_with(source, true), true),
+            Some(printed_exports(source, true))
+        );
+        let (_, kinds) = collect_exports_with_kinds(&mut parse_with(source, true), true);
+        assert_eq!(kinds["--accent"], vec![ExportKind::DashedIdent]);
+        assert!(!kinds.contains_key("--shared"));
+    }
+
+    #[test]
+    fn records_kinds() {
+        let source = r#"
+            .a, #b, .c#c { animation: d 1s; }
+            @keyframes d { from { opacity: 0; } }
+            .e { list-style-type: f; animation-name: "g"; }
+            @media print { .h { composes: a; } }
+            :global(.i) .a, :global(.e:not(.j)) {}
+        "#;
+        let (exports, kinds) = collect_exports_with_kinds(&mut parse(source), false);
+
+        // nested composes needs the printer, kinds are complete anyway
+        assert_eq!(exports, None);
+        let mut kinds: Vec<_> = kinds.into_iter().collect();
+        kinds.sort();
+        assert_eq!(
+            kinds,
+            vec![
+                ("a".to_string(), vec![ExportKind::Class]),
+                ("b".to_string(), vec![ExportKind::Id]),
+                ("c".to_string(), vec![ExportKind::Class, ExportKind::Id]),
+                ("d".to_string(), vec![ExportKind::Keyframes]),
+                (
+                    "e".to_string(),
+                    vec![ExportKind::Class, ExportKind::GlobalClass]
+                ),
+                ("f".to_string(), vec![ExportKind::CustomIdent]),
+                ("g".to_string(), vec![ExportKind::Keyframes]),
+                ("h".to_string(), vec![ExportKind::Class]),
+                ("i".to_string(), vec![ExportKind::GlobalClass]),
+                ("j".to_string(), vec![ExportKind::GlobalClass]),
+            ]
+        );
+    }
+
+    #[test]
+    fn unescapes_names() {
+        let source = r#"
+            .sm\:hidden, .w-1\/2, .\31 0px, .mt-2\.5 { composes: sm\:hidden; }
+            #\#id, :global(.g\:x) {}
+        "#;
+        let (exports, kinds) = collect_exports_with_kinds(&mut parse(source), false);
+
+        let exports = exports.unwrap();
+        assert_eq!(exports, printed_exports(source, false));
+        let mut names: Vec<_> = exports.keys().collect();
+        names.sort();
+        assert_eq!(names, ["#id", "10px", "mt-2.5", "sm:hidden", "w-1/2"]);
+        assert_eq!(kinds["g:x"], vec![ExportKind::GlobalClass]);
+    }
+
+    #[test]
+    fn finds_impure_selectors() {
+        let source = r#"
+            .a, div, :global(.b) {}
+            .c :global(.d), :not(.e), :global(.f):local(.g) {}
+            .h { & span { color: red; } @media print { :global(.i) { color: blue; } } }
+            @media print { ul li { color: red; } }
+            @keyframes j { from { opacity: 0; } }
+        "#;
+        let impure: Vec<_> = impure_selectors(&mut parse(source))
+            .into_iter()
+            .map(|impure| (impure.loc.line, impure.index))
+            .collect();
+
+        assert_eq!(impure, vec![(1, 1), (1, 2), (4, 0)]);
+    }
+
+    #[test]
+    fn falls_back_to_printer() {
+        // nested composes is rejected by the printer
+        assert_eq!(
+            collect_exports(&mut parse("@media print { .a { composes: b; } }"), false),
+            None
+        );
+        assert_eq!(
+            collect_exports(&mut parse(".a .b { composes: c; }"), false),
+            None
+        );
+        // area names are strings
+        assert_eq!(
+            collect_exports(
+                &mut parse(r#".a { grid-template-areas: "head head"; }"#),
+                false
+            ),
+            None
+        );
+    }
+}
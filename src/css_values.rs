@@ -0,0 +1,459 @@
+//! `@value` declarations of CSS modules, as handled by postcss-modules-values,
+//! and the ICSS `:import` and `:export` blocks they compile to.
+//!
+//! lightningcss knows neither of them, and the places values are used in, e.g. media queries,
+//! only parse once the values are substituted. The declarations are blanked out of the source,
+//! keeping every line where it was, and the values are then replaced textually.
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::generic_names::escape_identifier;
+
+/// Where `@value ... from` reads the values from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueSource {
+    /// `from "./colors.css"`
+    Path(String),
+    /// `from colors`, a value holding the path
+    Value(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueStatement {
+    /// `@value primary: #0af;`
+    Definition { name: String, value: String },
+    /// `@value small, medium as m from "./breakpoints.css";`,
+    /// `names` holds the imported name and the local one
+    Import {
+        names: Vec<(String, String)>,
+        from: ValueSource,
+    },
+    /// `:import("./x.css") { alias: name; }`, `names` holds the imported name and the alias
+    IcssImport {
+        names: Vec<(String, String)>,
+        from: String,
+    },
+    /// `name: value;` in an `:export` block
+    Export { name: String, value: String },
+}
+
+/// The source without its `@value` declarations, and the declarations in source order
+#[derive(Debug)]
+pub struct ExtractedValues {
+    pub source: String,
+    pub statements: Vec<ValueStatement>,
+}
+
+/// Moves past the comment or string starting at `i`, if any
+fn skip_comment_or_string(bytes: &[u8], i: usize) -> Option<usize> {
+    match bytes[i] {
+        b'/' if bytes.get(i + 1) == Some(&b'*') => {
+            let end = bytes[i + 2..]
+                .windows(2)
+                .position(|window| window == b"*/")
+                .map_or(bytes.len(), |end| i + 2 + end + 2);
+            Some(end)
+        }
+        quote @ (b'"' | b'\'') => {
+            let mut j = i + 1;
+            while j < bytes.len() && bytes[j] != quote {
+                j += if bytes[j] == b'\\' { 2 } else { 1 };
+            }
+            Some((j + 1).min(bytes.len()))
+        }
+        _ => None,
+    }
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-' || !byte.is_ascii()
+}
+
+/// Returns the offset of the `;` ending the statement starting at `i`, or the end of the source
+fn statement_end(bytes: &[u8], mut i: usize) -> usize {
+    let mut parens = 0usize;
+    while i < bytes.len() {
+        if let Some(next) = skip_comment_or_string(bytes, i) {
+            i = next;
+            continue;
+        }
+        match bytes[i] {
+            b'(' => parens += 1,
+            b')' => parens = parens.saturating_sub(1),
+            b';' if parens == 0 => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+fn parse_statement(statement: &str) -> Option<ValueStatement> {
+    lazy_static! {
+        static ref IMPORT: Regex =
+            Regex::new(r#"^(?s)(.+?|\(.+?\))\s+from\s+("[^"]*"|'[^']*'|[\w-]+)$"#).unwrap();
+        static ref IMPORTED_NAME: Regex = Regex::new(r"^([\w-]+)(?:\s+as\s+([\w-]+))?$").unwrap();
+        static ref DEFINITION: Regex = Regex::new(r"^(?s)([\w-]+)\s*:?(.*)$").unwrap();
+    }
+
+    let statement = statement.trim();
+    if let Some(captures) = IMPORT.captures(statement) {
+        let names = captures[1]
+            .trim()
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .split(',')
+            .map(|name| {
+                let captures = IMPORTED_NAME.captures(name.trim())?;
+                let imported = captures[1].to_string();
+                let local = captures
+                    .get(2)
+                    .map_or_else(|| imported.clone(), |local| local.as_str().to_string());
+                Some((imported, local))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let from = &captures[2];
+        let from = if from.starts_with('"') || from.starts_with('\'') {
+            ValueSource::Path(from[1..from.len() - 1].to_string())
+        } else {
+            ValueSource::Value(from.to_string())
+        };
+        return Some(ValueStatement::Import { names, from });
+    }
+
+    let captures = DEFINITION.captures(statement)?;
+    Some(ValueStatement::Definition {
+        name: captures[1].to_string(),
+        value: captures[2].trim().to_string(),
+    })
+}
+
+/// Whether the byte at `i` starts a top level rule
+fn starts_rule(bytes: &[u8], i: usize) -> bool {
+    let previous = bytes[..i]
+        .iter()
+        .rev()
+        .find(|byte| !byte.is_ascii_whitespace());
+    matches!(previous, None | Some(b'}' | b';' | b'/'))
+}
+
+/// Parses the `:import(...) { ... }` or `:export { ... }` block starting at `i`,
+/// returns its statements and the offset right after it
+fn parse_icss_block(source: &str, i: usize) -> Option<(Vec<ValueStatement>, usize)> {
+    let open = i + source[i..].find('{')?;
+    let close = open + source[open..].find('}')?;
+    let declarations = source[open + 1..close]
+        .split(';')
+        .map(str::trim)
+        .filter(|declaration| !declaration.is_empty())
+        .map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            let name = name.trim();
+            let is_ident = !name.is_empty() && name.bytes().all(is_ident_byte);
+            is_ident.then(|| (name.to_string(), value.trim().to_string()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let selector = source[i..open].trim();
+    let statements = if selector == ":export" {
+        declarations
+            .into_iter()
+            .map(|(name, value)| ValueStatement::Export { name, value })
+            .collect()
+    } else {
+        let from = selector.strip_prefix(":import(")?.strip_suffix(')')?;
+        let names = declarations
+            .into_iter()
+            .map(|(alias, name)| (name, alias))
+            .collect();
+        vec![ValueStatement::IcssImport {
+            names,
+            from: from.trim().trim_matches(['"', '\'']).to_string(),
+        }]
+    };
+    Some((statements, close + 1))
+}
+
+/// Extracts the top level `@value` declarations and ICSS blocks of `source`,
+/// returns the offset of the first invalid one as error.
+pub fn extract_values(source: &str) -> Result<ExtractedValues, usize> {
+    let bytes = source.as_bytes();
+    let mut statements = Vec::new();
+    let mut ranges = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some(next) = skip_comment_or_string(bytes, i) {
+            i = next;
+            continue;
+        }
+        match bytes[i] {
+            b'{' => depth += 1,
+            b'}' => depth = depth.saturating_sub(1),
+            b'@' if depth == 0
+                && bytes[i + 1..].starts_with(b"value")
+                && !bytes.get(i + 6).copied().is_some_and(is_ident_byte) =>
+            {
+                let end = statement_end(bytes, i + 6);
+                let statement = parse_statement(&source[i + 6..end]).ok_or(i)?;
+                statements.push(statement);
+                // the `;` belongs to the declaration
+                let end = (end + 1).min(bytes.len());
+                ranges.push((i, end));
+                i = end;
+                continue;
+            }
+            b':' if depth == 0
+                && starts_rule(bytes, i)
+                && (bytes[i + 1..].starts_with(b"import(")
+                    || bytes[i + 1..].starts_with(b"export")
+                        && !bytes.get(i + 7).copied().is_some_and(is_ident_byte)) =>
+            {
+                let (block, end) = parse_icss_block(source, i).ok_or(i)?;
+                statements.extend(block);
+                ranges.push((i, end));
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let mut blanked = String::with_capacity(source.len());
+    let mut last = 0;
+    for (start, end) in ranges {
+        blanked.push_str(&source[last..start]);
+        blanked.extend(source[start..end].chars().map(|c| {
+            if c == '\n' || c == '\r' {
+                c
+            } else {
+                ' '
+            }
+        }));
+        last = end;
+    }
+    blanked.push_str(&source[last..]);
+
+    Ok(ExtractedValues {
+        source: blanked,
+        statements,
+    })
+}
+
+/// An identifier of the source, see [`replace_idents`]
+struct Ident<'s> {
+    name: &'s str,
+    /// byte right before the identifier
+    previous: Option<u8>,
+    /// first byte after the identifier that is not whitespace
+    next: Option<u8>,
+}
+
+/// Replaces the identifiers of `source` outside of strings, comments, numbers, function names
+/// and `url()`. `replace` returns the replacement and whether it includes the byte before the identifier.
+fn replace_idents(
+    source: &str,
+    mut replace: impl FnMut(&Ident) -> Option<(String, bool)>,
+) -> String {
+    let bytes = source.as_bytes();
+    let mut replaced = String::with_capacity(source.len());
+    let mut last = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some(next) = skip_comment_or_string(bytes, i) {
+            i = next;
+            continue;
+        }
+        if !is_ident_byte(bytes[i]) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < bytes.len() && is_ident_byte(bytes[i]) {
+            i += 1;
+        }
+        // numbers and their units
+        if bytes[start].is_ascii_digit() {
+            continue;
+        }
+        if bytes.get(i) == Some(&b'(') {
+            if source[start..i].eq_ignore_ascii_case("url") {
+                // unquoted urls are not made of tokens
+                i = bytes[i..]
+                    .iter()
+                    .position(|byte| *byte == b')')
+                    .map_or(bytes.len(), |end| i + end);
+            }
+            continue;
+        }
+
+        let ident = Ident {
+            name: &source[start..i],
+            previous: start.checked_sub(1).map(|previous| bytes[previous]),
+            next: bytes[i..]
+                .iter()
+                .find(|byte| !byte.is_ascii_whitespace())
+                .copied(),
+        };
+        if let Some((replacement, with_previous)) = replace(&ident) {
+            let start = if with_previous { start - 1 } else { start };
+            replaced.push_str(&source[last..start]);
+            replaced.push_str(&replacement);
+            last = i;
+        }
+    }
+    replaced.push_str(&source[last..]);
+    replaced
+}
+
+/// Replaces the identifiers of `source` that name a value.
+///
+/// Class and id selectors, at-rule names, property and feature names (followed by `:`),
+/// function names, strings, comments and `url()` are left as they are.
+pub fn replace_values(source: &str, values: &HashMap<String, String>) -> String {
+    if values.is_empty() {
+        return source.to_string();
+    }
+    replace_idents(source, |ident| {
+        if matches!(ident.previous, Some(b'.' | b'#' | b'@' | b'\\')) || ident.next == Some(b':') {
+            return None;
+        }
+        values.get(ident.name).map(|value| (value.clone(), false))
+    })
+}
+
+/// Replaces the class selectors naming an ICSS `:import` alias with the imported classes,
+/// made global so that they are not mistaken for classes of the stylesheet itself.
+pub fn replace_class_aliases(source: &str, aliases: &HashMap<String, String>) -> String {
+    if aliases.is_empty() {
+        return source.to_string();
+    }
+    replace_idents(source, |ident| {
+        if ident.previous != Some(b'.') {
+            return None;
+        }
+        aliases.get(ident.name).map(|classes| {
+            let selector: String = classes
+                .split_whitespace()
+                .map(|class| format!(".{}", escape_identifier(class)))
+                .collect();
+            (format!(":global({})", selector), true)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_declarations() {
+        let source = "@value primary: #0af;\n@value (small, medium as m) from \"./breakpoints.css\";\n@value colors: './colors.css';\n@value accent from colors;\n.a { color: primary; }\n";
+        let extracted = extract_values(source).unwrap();
+
+        assert_eq!(
+            extracted.statements,
+            vec![
+                ValueStatement::Definition {
+                    name: "primary".to_string(),
+                    value: "#0af".to_string(),
+                },
+                ValueStatement::Import {
+                    names: vec![
+                        ("small".to_string(), "small".to_string()),
+                        ("medium".to_string(), "m".to_string()),
+                    ],
+                    from: ValueSource::Path("./breakpoints.css".to_string()),
+                },
+                ValueStatement::Definition {
+                    name: "colors".to_string(),
+                    value: "'./colors.css'".to_string(),
+                },
+                ValueStatement::Import {
+                    names: vec![("accent".to_string(), "accent".to_string())],
+                    from: ValueSource::Value("colors".to_string()),
+                },
+            ]
+        );
+        // lines and columns of the remaining css are kept
+        assert_eq!(
+            extracted.source.lines().collect::<Vec<_>>(),
+            vec![
+                " ".repeat(21),
+                " ".repeat(53),
+                " ".repeat(30),
+                " ".repeat(26),
+                ".a { color: primary; }".to_string(),
+            ]
+        );
+        assert_eq!(extract_values(".a {}\n@value ;").err(), Some(6));
+    }
+
+    #[test]
+    fn extracts_icss_blocks() {
+        let source = ":import(\"./base.css\") {\n  imported-button: button;\n}\n:export {\n  gridGutter: 16px;\n  button: imported-button\n}\n.a:export-like {}\n";
+        let extracted = extract_values(source).unwrap();
+
+        assert_eq!(
+            extracted.statements,
+            vec![
+                ValueStatement::IcssImport {
+                    names: vec![("button".to_string(), "imported-button".to_string())],
+                    from: "./base.css".to_string(),
+                },
+                ValueStatement::Export {
+                    name: "gridGutter".to_string(),
+                    value: "16px".to_string(),
+                },
+                ValueStatement::Export {
+                    name: "button".to_string(),
+                    value: "imported-button".to_string(),
+                },
+            ]
+        );
+        assert_eq!(extracted.source.trim(), ".a:export-like {}");
+        assert_eq!(extract_values(":export { 16px }").err(), Some(0));
+    }
+
+    #[test]
+    fn replaces_values() {
+        let values = HashMap::from([
+            ("small".to_string(), "(max-width: 599px)".to_string()),
+            ("primary".to_string(), "#0af".to_string()),
+        ]);
+        let source = "@media small { .primary, #primary { color: primary; primary: 1; background: url(primary.png), primary(1); content: \"primary\"; } }";
+
+        assert_eq!(
+            replace_values(source, &values),
+            "@media (max-width: 599px) { .primary, #primary { color: #0af; primary: 1; background: url(primary.png), primary(1); content: \"primary\"; } }"
+        );
+        // not part of a longer identifier
+        assert_eq!(
+            replace_values(".a { color: primary-dark; }", &values),
+            ".a { color: primary-dark; }"
+        );
+    }
+
+    #[test]
+    fn replaces_class_aliases() {
+        let aliases = HashMap::from([
+            ("imported".to_string(), "base__a base__b".to_string()),
+            ("hidden".to_string(), "base__sm:hidden".to_string()),
+        ]);
+
+        assert_eq!(
+            replace_class_aliases(
+                ".imported > .imported-icon, .a.imported, .hidden { color: imported; }",
+                &aliases
+            ),
+            ":global(.base__a.base__b) > .imported-icon, .a:global(.base__a.base__b), :global(.base__sm\\:hidden) { color: imported; }"
+        );
+    }
+}
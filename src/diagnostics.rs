@@ -0,0 +1,137 @@
+use std::fmt;
+
+use serde::Deserialize;
+use swc_core::{
+    common::{errors::DiagnosticId, Span},
+    plugin::errors::HANDLER,
+};
+use ts_rs::TS;
+
+use crate::Error;
+
+/// Stable identifier of each kind of diagnostic reported by the plugin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, TS)]
+#[serde(rename_all = "kebab-case")]
+#[ts(export, export_to = "types.d.ts")]
+pub enum DiagnosticCode {
+    /// the plugin config or the metadata passed by swc is not usable
+    InvalidConfig,
+    /// an import could not be resolved to a file
+    UnresolvedImport,
+    /// a stylesheet could not be read
+    UnreadableStylesheet,
+    /// lightningcss rejected a stylesheet
+    CssParseError,
+    /// lightningcss skipped invalid css in a stylesheet
+    CssRecoveredError,
+    /// class names could not be generated with the naming settings
+    NamingError,
+    /// a styleName is not defined by any imported stylesheet
+    UnresolvedStyleName,
+    /// the prefix of a styleName matches no import
+    UnknownImportPrefix,
+    /// a stylesheet is imported with named specifiers
+    NamedImport,
+    /// an unprefixed styleName is defined by several stylesheets
+    AmbiguousStyleName,
+    /// a styleName is resolved at runtime
+    RuntimeFallback,
+    /// a class composed from another stylesheet is not defined there
+    UnresolvedComposes,
+    /// a value imported with `@value ... from` is not exported by the other stylesheet
+    UnresolvedValue,
+    /// stylesheets compose from each other
+    ComposesCycle,
+    /// stylesheets `@import` each other
+    ImportCycle,
+    /// a compile-time lookup is not called with string literals
+    InvalidMacroCall,
+    /// a name looked up at compile time is not defined as the expected kind
+    ExportKindMismatch,
+    /// `styleVars` is not an object literal with static keys
+    InvalidStyleVars,
+    /// a styleName refers to a `:global` class of a stylesheet
+    GlobalStyleName,
+    /// a selector of a stylesheet in pure mode contains no local class or id
+    ImpureSelector,
+    /// the class map manifest is not usable or has no entry for a stylesheet
+    InvalidManifest,
+    /// a name of the class map manifest differs from the compiled one
+    ManifestMismatch,
+}
+
+impl DiagnosticCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCode::InvalidConfig => "invalid-config",
+            DiagnosticCode::UnresolvedImport => "unresolved-import",
+            DiagnosticCode::UnreadableStylesheet => "unreadable-stylesheet",
+            DiagnosticCode::CssParseError => "css-parse-error",
+            DiagnosticCode::CssRecoveredError => "css-recovered-error",
+            DiagnosticCode::NamingError => "naming-error",
+            DiagnosticCode::UnresolvedStyleName => "unresolved-style-name",
+            DiagnosticCode::UnknownImportPrefix => "unknown-import-prefix",
+            DiagnosticCode::NamedImport => "named-import",
+            DiagnosticCode::AmbiguousStyleName => "ambiguous-style-name",
+            DiagnosticCode::RuntimeFallback => "runtime-fallback",
+            DiagnosticCode::UnresolvedComposes => "unresolved-composes",
+            DiagnosticCode::UnresolvedValue => "unresolved-value",
+            DiagnosticCode::ComposesCycle => "composes-cycle",
+            DiagnosticCode::ImportCycle => "import-cycle",
+            DiagnosticCode::InvalidMacroCall => "invalid-macro-call",
+            DiagnosticCode::ExportKindMismatch => "export-kind-mismatch",
+            DiagnosticCode::InvalidStyleVars => "invalid-style-vars",
+            DiagnosticCode::GlobalStyleName => "global-style-name",
+            DiagnosticCode::ImpureSelector => "impure-selector",
+            DiagnosticCode::InvalidManifest => "invalid-manifest",
+            DiagnosticCode::ManifestMismatch => "manifest-mismatch",
+        }
+    }
+
+    /// Severity used when the code is not listed in `Config::diagnostics`
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            DiagnosticCode::CssRecoveredError | DiagnosticCode::AmbiguousStyleName => {
+                Severity::Warn
+            }
+            DiagnosticCode::RuntimeFallback | DiagnosticCode::GlobalStyleName => Severity::Off,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How a diagnostic is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export, export_to = "types.d.ts")]
+pub enum Severity {
+    Error,
+    Warn,
+    Off,
+}
+
+/// Reports `err` at `span` with the given severity, the transform always continues
+pub fn report(span: Span, err: &Error, severity: Severity) {
+    if severity == Severity::Off {
+        return;
+    }
+
+    HANDLER.with(|handler| {
+        let message = err.to_string();
+        let mut diagnostic = match severity {
+            Severity::Warn => handler.struct_span_warn(span, &message),
+            _ => handler.struct_span_err(span, &message),
+        };
+        diagnostic.code(DiagnosticId::Error(err.code().to_string()));
+        if let Some(note) = err.note() {
+            diagnostic.note(&note);
+        }
+        diagnostic.emit();
+    });
+}
@@ -0,0 +1,443 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{css_exports::ExportKind, diagnostics::DiagnosticCode};
+
+/// Errors reported by the plugin
+///
+/// None of them abort the transform, they are reported through swc's `HANDLER`
+/// at the span of the import or attribute that caused them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// the plugin config is not valid json or has unexpected fields
+    InvalidConfig(String),
+    /// swc did not pass the given transform metadata
+    MissingMetadata(&'static str),
+    /// the file being transformed is outside of `root`
+    OutsideRoot { path: PathBuf, root: PathBuf },
+    /// the import source could not be resolved to an absolute path
+    UnresolvedImport { src: String, path: PathBuf },
+    /// the stylesheet does not exist or is not readable
+    ReadStylesheet { path: PathBuf },
+    /// lightningcss rejected the stylesheet
+    ParseStylesheet {
+        path: PathBuf,
+        message: String,
+        location: Option<CssLocation>,
+    },
+    /// lightningcss skipped invalid css with `css_parser.error_recovery`
+    RecoveredStylesheet {
+        path: PathBuf,
+        message: String,
+        location: Option<CssLocation>,
+    },
+    /// lightningcss failed to collect the exports of the stylesheet
+    PrintStylesheet {
+        path: PathBuf,
+        message: String,
+        location: Option<CssLocation>,
+    },
+    /// the naming pattern is not supported by lightningcss
+    InvalidPattern(String),
+    /// a path could not be made relative to the hashing context
+    RelativePath { path: PathBuf, context: PathBuf },
+    /// `hash_function` is not one of the supported algorithms
+    UnsupportedHashFunction(String),
+    /// `hash_digest` is not one of the supported encodings
+    UnsupportedHashDigest(String),
+    /// no imported stylesheet defines the styleName
+    UnresolvedStyleName {
+        name: String,
+        /// import sources of the stylesheets that were searched
+        searched: Vec<String>,
+        /// closest known name and the stylesheet defining it
+        suggestion: Option<(String, String)>,
+    },
+    /// the prefix of a styleName, `foo` in `foo.bar`, matches no import
+    UnknownImportPrefix {
+        prefix: String,
+        name: String,
+        imports: Vec<String>,
+    },
+    /// the stylesheet is imported with named specifiers
+    NamedImport { src: String },
+    /// an unprefixed styleName is defined by several stylesheets, the first one is used
+    AmbiguousStyleName { name: String, sources: Vec<String> },
+    /// the styleName is not a string literal and is resolved at runtime
+    RuntimeFallback,
+    /// `composes: name from "specifier"` refers to a class the other stylesheet does not define
+    UnresolvedComposes {
+        path: PathBuf,
+        name: String,
+        specifier: String,
+        dependency: PathBuf,
+    },
+    /// `@value name from "specifier"` or `:import("specifier")` refers to a value the other stylesheet does not export
+    UnresolvedValue {
+        path: PathBuf,
+        name: String,
+        specifier: String,
+        dependency: PathBuf,
+    },
+    /// stylesheets compose from each other, `cycle` starts and ends with the same stylesheet
+    ComposesCycle { cycle: Vec<PathBuf> },
+    /// stylesheets `@import` each other, `cycle` starts and ends with the same stylesheet
+    ImportCycle { cycle: Vec<PathBuf> },
+    /// a call to the `scopedName` macro whose arguments can not be evaluated at compile time
+    InvalidMacroCall { name: String, reason: String },
+    /// `scopedName("name", "kind")` refers to a name that is not defined as `kind`
+    ExportKindMismatch {
+        name: String,
+        expected: ExportKind,
+        found: Vec<ExportKind>,
+    },
+    /// `styleVars` can not be turned into custom properties at compile time
+    InvalidStyleVars { reason: &'static str },
+    /// the styleName refers to a `:global(.name)` class, which is used unchanged
+    GlobalStyleName { name: String, src: String },
+    /// a selector of a stylesheet in pure mode contains no local class or id
+    ImpureSelector {
+        path: PathBuf,
+        selector: String,
+        location: Option<CssLocation>,
+    },
+    /// the `class_map_source` manifest can not be read or is not a map of class maps
+    InvalidManifest { path: PathBuf, reason: String },
+    /// the `class_map_source` manifest has no class map for the stylesheet
+    MissingManifestEntry { manifest: PathBuf, key: String },
+    /// a name of the manifest differs from the one compiled from the stylesheet
+    ManifestMismatch {
+        key: String,
+        name: String,
+        manifest: Option<String>,
+        generated: Option<String>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidConfig(message) => write!(f, "Invalid plugin config: {}", message),
+            Error::MissingMetadata(kind) => write!(f, "Failed to get {} from swc", kind),
+            Error::OutsideRoot { path, root } => {
+                write!(f, "{:?} is not inside root {:?}", path, root)
+            }
+            Error::UnresolvedImport { src, path } => {
+                write!(f, "Could not resolve {:?}, got {:?}", src, path)
+            }
+            Error::ReadStylesheet { path } => write!(f, "Could not read {:?}", path),
+            Error::ParseStylesheet {
+                path,
+                message,
+                location,
+            } => {
+                write!(f, "Could not parse ")?;
+                write_css_path(f, path, location)?;
+                write!(f, ": {}", message)
+            }
+            Error::RecoveredStylesheet {
+                path,
+                message,
+                location,
+            } => {
+                write!(f, "Ignored invalid css in ")?;
+                write_css_path(f, path, location)?;
+                write!(f, ": {}", message)
+            }
+            Error::PrintStylesheet {
+                path,
+                message,
+                location,
+            } => {
+                write!(f, "Could not collect exports of ")?;
+                write_css_path(f, path, location)?;
+                write!(f, ": {}", message)
+            }
+            Error::InvalidPattern(message) => write!(f, "{}", message),
+            Error::RelativePath { path, context } => {
+                write!(f, "Could not make {:?} relative to {:?}", path, context)
+            }
+            Error::UnsupportedHashFunction(name) => {
+                write!(f, "Unsupported hash function: {}", name)
+            }
+            Error::UnsupportedHashDigest(name) => write!(f, "Unsupported hash digest: {}", name),
+            Error::UnresolvedStyleName { name, searched, .. } => {
+                write!(f, "Could not resolve styleName `{}`", name)?;
+                if searched.is_empty() {
+                    write!(f, ", no stylesheet is imported")
+                } else {
+                    write!(f, " in {}", searched.join(", "))
+                }
+            }
+            Error::UnknownImportPrefix {
+                prefix,
+                name,
+                imports,
+            } => {
+                write!(
+                    f,
+                    "Could not resolve styleName `{}.{}`, no stylesheet is imported as `{}`",
+                    prefix, name, prefix
+                )?;
+                if !imports.is_empty() {
+                    write!(f, ", imported stylesheets are named {}", imports.join(", "))?;
+                }
+                Ok(())
+            }
+            Error::NamedImport { src } => {
+                write!(f, "Named imports are not supported, used for {}", src)
+            }
+            Error::AmbiguousStyleName { name, sources } => write!(
+                f,
+                "styleName `{}` is defined in {}, using {}",
+                name,
+                sources.join(", "),
+                sources.first().map_or("", |src| src.as_str())
+            ),
+            Error::RuntimeFallback => write!(
+                f,
+                "styleName is not a string literal and can only be resolved at runtime"
+            ),
+            Error::UnresolvedComposes {
+                path,
+                name,
+                specifier,
+                dependency,
+            } => write!(
+                f,
+                "{} composes `{}` from {:?}, but {} does not define it",
+                path.display(),
+                name,
+                specifier,
+                dependency.display()
+            ),
+            Error::UnresolvedValue {
+                path,
+                name,
+                specifier,
+                dependency,
+            } => write!(
+                f,
+                "{} imports `{}` from {:?}, but {} does not export it",
+                path.display(),
+                name,
+                specifier,
+                dependency.display()
+            ),
+            Error::ComposesCycle { cycle } => {
+                write!(f, "Stylesheets compose from each other: ")?;
+                write_cycle(f, cycle)
+            }
+            Error::ImportCycle { cycle } => {
+                write!(f, "Stylesheets import each other: ")?;
+                write_cycle(f, cycle)
+            }
+            Error::InvalidMacroCall { name, reason } => {
+                write!(
+                    f,
+                    "Could not evaluate `{}` at compile time: {}",
+                    name, reason
+                )
+            }
+            Error::ExportKindMismatch {
+                name,
+                expected,
+                found,
+            } => {
+                let found: Vec<_> = found.iter().map(|kind| kind.as_str()).collect();
+                write!(
+                    f,
+                    "`{}` is not defined as {}, it is defined as {}",
+                    name,
+                    expected.as_str(),
+                    found.join(" and ")
+                )
+            }
+            Error::InvalidStyleVars { reason } => write!(f, "Invalid styleVars: {}", reason),
+            Error::GlobalStyleName { name, src } => write!(
+                f,
+                "styleName `{}` is a global class of {} and is not scoped",
+                name, src
+            ),
+            Error::ImpureSelector {
+                path,
+                selector,
+                location,
+            } => {
+                write!(f, "Selector `{}` in ", selector)?;
+                write_css_path(f, path, location)?;
+                write!(f, " is not pure, it has to contain a local class or id")
+            }
+            Error::InvalidManifest { path, reason } => {
+                write!(
+                    f,
+                    "Invalid class map manifest {}: {}",
+                    path.display(),
+                    reason
+                )
+            }
+            Error::MissingManifestEntry { manifest, key } => {
+                write!(f, "{} has no class map for {}", manifest.display(), key)
+            }
+            Error::ManifestMismatch {
+                key,
+                name,
+                manifest,
+                generated,
+            } => {
+                write!(f, "`{}` of {} is ", name, key)?;
+                match (manifest, generated) {
+                    (Some(manifest), Some(generated)) => write!(
+                        f,
+                        "`{}` in the manifest but is generated as `{}`",
+                        manifest, generated
+                    ),
+                    (Some(manifest), None) => write!(
+                        f,
+                        "`{}` in the manifest but is not defined by the stylesheet",
+                        manifest
+                    ),
+                    (None, generated) => write!(
+                        f,
+                        "missing from the manifest, it is generated as `{}`",
+                        generated.as_deref().unwrap_or_default()
+                    ),
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    pub fn code(&self) -> DiagnosticCode {
+        match self {
+            Error::InvalidConfig(_) | Error::MissingMetadata(_) => DiagnosticCode::InvalidConfig,
+            Error::OutsideRoot { .. } | Error::UnresolvedImport { .. } => {
+                DiagnosticCode::UnresolvedImport
+            }
+            Error::ReadStylesheet { .. } => DiagnosticCode::UnreadableStylesheet,
+            Error::ParseStylesheet { .. } | Error::PrintStylesheet { .. } => {
+                DiagnosticCode::CssParseError
+            }
+            Error::RecoveredStylesheet { .. } => DiagnosticCode::CssRecoveredError,
+            Error::InvalidPattern(_)
+            | Error::RelativePath { .. }
+            | Error::UnsupportedHashFunction(_)
+            | Error::UnsupportedHashDigest(_) => DiagnosticCode::NamingError,
+            Error::UnresolvedStyleName { .. } => DiagnosticCode::UnresolvedStyleName,
+            Error::UnknownImportPrefix { .. } => DiagnosticCode::UnknownImportPrefix,
+            Error::NamedImport { .. } => DiagnosticCode::NamedImport,
+            Error::AmbiguousStyleName { .. } => DiagnosticCode::AmbiguousStyleName,
+            Error::RuntimeFallback => DiagnosticCode::RuntimeFallback,
+            Error::UnresolvedComposes { .. } => DiagnosticCode::UnresolvedComposes,
+            Error::UnresolvedValue { .. } => DiagnosticCode::UnresolvedValue,
+            Error::ComposesCycle { .. } => DiagnosticCode::ComposesCycle,
+            Error::ImportCycle { .. } => DiagnosticCode::ImportCycle,
+            Error::InvalidMacroCall { .. } => DiagnosticCode::InvalidMacroCall,
+            Error::ExportKindMismatch { .. } => DiagnosticCode::ExportKindMismatch,
+            Error::InvalidStyleVars { .. } => DiagnosticCode::InvalidStyleVars,
+            Error::GlobalStyleName { .. } => DiagnosticCode::GlobalStyleName,
+            Error::ImpureSelector { .. } => DiagnosticCode::ImpureSelector,
+            Error::InvalidManifest { .. } | Error::MissingManifestEntry { .. } => {
+                DiagnosticCode::InvalidManifest
+            }
+            Error::ManifestMismatch { .. } => DiagnosticCode::ManifestMismatch,
+        }
+    }
+
+    /// Additional context shown below the diagnostic, e.g. the offending css
+    pub fn note(&self) -> Option<String> {
+        match self {
+            Error::ParseStylesheet {
+                location: Some(location),
+                ..
+            }
+            | Error::RecoveredStylesheet {
+                location: Some(location),
+                ..
+            }
+            | Error::PrintStylesheet {
+                location: Some(location),
+                ..
+            }
+            | Error::ImpureSelector {
+                location: Some(location),
+                ..
+            } => Some(location.to_string()),
+            Error::UnresolvedStyleName {
+                suggestion: Some((name, src)),
+                ..
+            } => Some(format!("did you mean `{}` in {}?", name, src)),
+            _ => None,
+        }
+    }
+}
+
+/// A position inside a stylesheet, along with the line it points to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CssLocation {
+    /// starts from 1
+    pub line: u32,
+    /// starts from 1
+    pub column: u32,
+    pub source_line: String,
+}
+
+impl CssLocation {
+    /// `line` starts from 0 and `column` from 1, as reported by lightningcss
+    pub fn new(source: &str, line: u32, column: u32) -> Self {
+        Self {
+            line: line + 1,
+            column,
+            source_line: source
+                .lines()
+                .nth(line as usize)
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+}
+
+impl fmt::Display for CssLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        // keep tabs so that the caret lines up with the source
+        let padding: String = self
+            .source_line
+            .chars()
+            .take(self.column.saturating_sub(1) as usize)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", line_number, self.source_line)?;
+        write!(f, "{} | {}^", gutter, padding)
+    }
+}
+
+fn write_css_path(
+    f: &mut fmt::Formatter<'_>,
+    path: &Path,
+    location: &Option<CssLocation>,
+) -> fmt::Result {
+    write!(f, "{}", path.display())?;
+    if let Some(location) = location {
+        write!(f, ":{}:{}", location.line, location.column)?;
+    }
+    Ok(())
+}
+
+fn write_cycle(f: &mut fmt::Formatter<'_>, cycle: &[PathBuf]) -> fmt::Result {
+    let cycle: Vec<_> = cycle
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    write!(f, "{}", cycle.join(" -> "))
+}
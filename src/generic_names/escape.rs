@@ -0,0 +1,114 @@
+//! Escaping of css identifiers, lightningcss exports class names unescaped while
+//! postcss based tools see them as written in the stylesheet.
+
+/// Serializes `name` like `CSS.escape`, the way escaped class names are usually written,
+/// e.g. `sm:hidden` becomes `sm\:hidden` and `10px` becomes `\31 0px`
+pub fn escape_identifier(name: &str) -> String {
+    let mut output = String::with_capacity(name.len());
+
+    if name == "-" {
+        return "\\-".to_string();
+    }
+
+    for (index, c) in name.chars().enumerate() {
+        let leading_digit =
+            c.is_ascii_digit() && (index == 0 || (index == 1 && name.starts_with('-')));
+        match c {
+            '\0' => output.push('\u{FFFD}'),
+            '\x01'..='\x1f' | '\x7f' => output.push_str(&format!("\\{:x} ", c as u32)),
+            _ if leading_digit => output.push_str(&format!("\\{:x} ", c as u32)),
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '\u{80}'.. => output.push(c),
+            _ => {
+                output.push('\\');
+                output.push(c);
+            }
+        }
+    }
+
+    output
+}
+
+/// Resolves the escapes of a css identifier, as css-loader's `unescape`
+pub fn unescape_identifier(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        let mut hex = String::new();
+        while hex.len() < 6 {
+            match chars.peek() {
+                Some(digit) if digit.is_ascii_hexdigit() => {
+                    hex.push(*digit);
+                    chars.next();
+                }
+                _ => break,
+            }
+        }
+
+        if hex.is_empty() {
+            result.push(chars.next().unwrap_or('\u{FFFD}'));
+            continue;
+        }
+
+        if matches!(chars.peek(), Some(' ' | '\t' | '\n' | '\r' | '\x0c')) {
+            chars.next();
+        }
+        let code_point = u32::from_str_radix(&hex, 16).unwrap_or(0);
+        result.push(
+            char::from_u32(code_point)
+                .filter(|c| *c != '\0')
+                .unwrap_or('\u{FFFD}'),
+        );
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_identifier, unescape_identifier};
+
+    #[test]
+    fn escapes_like_css_escape() {
+        assert_eq!(escape_identifier("sm:hidden"), r"sm\:hidden");
+        assert_eq!(escape_identifier("w-1/2"), r"w-1\/2");
+        assert_eq!(escape_identifier("mt-2.5"), r"mt-2\.5");
+        assert_eq!(escape_identifier("w-[10px]"), r"w-\[10px\]");
+        assert_eq!(escape_identifier("10px"), r"\31 0px");
+        assert_eq!(escape_identifier("-1"), r"-\31 ");
+        assert_eq!(escape_identifier("--foo"), "--foo");
+        assert_eq!(escape_identifier("-"), r"\-");
+        assert_eq!(escape_identifier("a\tb"), r"a\9 b");
+        assert_eq!(escape_identifier("😀"), "😀");
+    }
+
+    #[test]
+    fn unescapes_identifiers() {
+        assert_eq!(unescape_identifier(r"sm\:hidden"), "sm:hidden");
+        assert_eq!(unescape_identifier(r"\31 0px"), "10px");
+        assert_eq!(unescape_identifier(r"\31-0px"), "1-0px");
+        assert_eq!(unescape_identifier(r"\1F600"), "😀");
+        assert_eq!(unescape_identifier(r"\0"), "\u{FFFD}");
+        assert_eq!(unescape_identifier("foo\\"), "foo\u{FFFD}");
+    }
+
+    #[test]
+    fn round_trips() {
+        for name in [
+            "sm:hidden",
+            "w-1/2",
+            "10px",
+            "--x",
+            "a\tb",
+            "w-[10px]",
+            "😀",
+        ] {
+            assert_eq!(unescape_identifier(&escape_identifier(name)), name);
+        }
+    }
+}
@@ -1,21 +1,46 @@
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 
-use crate::loader_utils::interpolate::{
-    interpolate_name, LoaderContext, Options as LoaderUtilsOptions,
+use crate::{
+    loader_utils::interpolate::{interpolate_name, LoaderContext, Options as LoaderUtilsOptions},
+    Error,
 };
 
+mod escape;
+mod presets;
+
+pub use escape::{escape_identifier, unescape_identifier};
+pub use presets::{LocalIdentHashOptions, NamingPreset};
+
 pub struct Options {
     pub context: PathBuf,
     pub hash_prefix: String,
+    pub preset: NamingPreset,
+    /// only used by the css-loader preset
+    pub local_ident_hash: LocalIdentHashOptions,
+    /// css-loader `localIdentRegExp`, `[N]` is replaced by the N-th capture group
+    /// of the stylesheet path, used by the generic-names and css-loader presets
+    pub reg_exp: Option<String>,
+    /// `?query` the stylesheet is imported with, replaces `[query]` of the generic-names preset
+    pub resource_query: String,
+    /// `#fragment` the stylesheet is imported with, replaces `[fragment]` of the generic-names preset
+    pub resource_fragment: String,
 }
 
 impl Default for Options {
     fn default() -> Self {
         Self {
-            context: env::current_dir().unwrap(),
+            context: env::current_dir().unwrap_or_else(|_| PathBuf::from("/")),
             hash_prefix: "".to_string(),
+            preset: NamingPreset::default(),
+            local_ident_hash: LocalIdentHashOptions::default(),
+            reg_exp: None,
+            resource_query: "".to_string(),
+            resource_fragment: "".to_string(),
         }
     }
 }
@@ -47,23 +72,51 @@ impl Generator {
     /// # Examples
     ///
     /// ```
-    /// use swc_plugin_react_css_modules::generic_names::Generator;
+    /// use swc_plugin_react_css_modules::generic_names::{Generator, Options};
     ///
-    /// let generator = Generator::new("[name]__[local]___[hash:base64:5]");
+    /// let generator = Generator::new_with_options(
+    ///     "[name]__[local]___[hash:base64:5]",
+    ///     Options {
+    ///         context: "/".into(),
+    ///         ..Default::default()
+    ///     },
+    /// );
     ///
     /// assert_eq!(
     ///     generator.generate("foo", "/case/source.css".into()),
-    ///     "source__foo___pdq35".to_string(),
+    ///     Ok("source__foo___ZIJxV".to_string()),
     /// );
     /// ```
-    pub fn generate(&self, local_name: &str, filepath: PathBuf) -> String {
+    pub fn generate(&self, local_name: &str, filepath: PathBuf) -> Result<String, Error> {
+        self.generate_with_source(local_name, filepath, "")
+    }
+
+    /// Same as [`Generator::generate`], `source` is the content of the stylesheet
+    /// and is only used by presets that hash it, see [`NamingPreset::requires_source`].
+    pub fn generate_with_source(
+        &self,
+        local_name: &str,
+        filepath: PathBuf,
+        source: &str,
+    ) -> Result<String, Error> {
+        match self.options.preset {
+            NamingPreset::GenericNames => self.generic_names(local_name, filepath),
+            NamingPreset::CssLoader => {
+                presets::css_loader(&self.pattern, &self.options, local_name, filepath)
+            }
+            NamingPreset::Next => presets::next(&self.options, local_name, filepath),
+            NamingPreset::Cra => presets::cra(&self.options, local_name, filepath),
+            NamingPreset::PostcssModules => Ok(presets::postcss_modules(local_name, source)),
+            NamingPreset::Lightningcss => {
+                presets::lightningcss(&self.pattern, &self.options, local_name, filepath)
+            }
+        }
+    }
+
+    fn generic_names(&self, local_name: &str, filepath: PathBuf) -> Result<String, Error> {
         let name = self.pattern.replace("[local]", local_name);
 
-        let absolute_path = pathdiff::diff_paths(filepath.clone(), &self.options.context)
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .replace('\\', r"/");
+        let absolute_path = relative_path(&filepath, &self.options.context)?;
 
         let content = format!(
             "{}{}\x00{}",
@@ -73,27 +126,40 @@ impl Generator {
         let generic_name = interpolate_name(
             LoaderContext {
                 resource_path: Some(filepath),
+                resource_query: Some(self.options.resource_query.clone()),
+                resource_fragment: Some(self.options.resource_fragment.clone()),
             },
             name.as_str(),
             LoaderUtilsOptions {
                 context: Some(self.options.context.clone()),
                 content: Some(content.as_bytes()),
+                reg_exp: self.options.reg_exp.as_deref(),
             },
-        );
+        )?;
 
         lazy_static! {
             static ref INVALID_SYMBOLS: Regex =
-                Regex::new(r"[^a-zA-Z0-9\\-_\u00A0-\uFFFF]").unwrap();
+                Regex::new(r"[^a-zA-Z0-9\-_\u00A0-\uFFFF]").unwrap();
             static ref INVALID_START: Regex = Regex::new(r"^((-?[0-9])|--)").unwrap();
         }
 
         let validate_symbols = INVALID_SYMBOLS.replace_all(&generic_name, "-");
         let result = INVALID_START.replace(&validate_symbols, "_$1");
 
-        result.into_owned()
+        Ok(result.into_owned())
     }
 }
 
+/// Returns `filepath` relative to `context` with forward slashes
+pub(crate) fn relative_path(filepath: &Path, context: &Path) -> Result<String, Error> {
+    pathdiff::diff_paths(filepath, context)
+        .map(|path| path.to_string_lossy().replace('\\', r"/"))
+        .ok_or_else(|| Error::RelativePath {
+            path: filepath.to_path_buf(),
+            context: context.to_path_buf(),
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use std::{env, path::PathBuf};
@@ -112,7 +178,10 @@ mod tests {
     fn use_cwd_if_no_context_was_provided() {
         let generator = Generator::new(PATTERN);
 
-        assert_eq!(generator.generate("foo", filepath()), "source__foo___VihAC");
+        assert_eq!(
+            generator.generate("foo", filepath()).unwrap(),
+            "source__foo___VihAC"
+        );
     }
 
     #[test]
@@ -124,10 +193,14 @@ mod tests {
             Options {
                 context,
                 hash_prefix: "".to_string(),
+                ..Default::default()
             },
         );
 
-        assert_eq!(generator.generate("foo", filepath()), "source__foo___ZIJxV");
+        assert_eq!(
+            generator.generate("foo", filepath()).unwrap(),
+            "source__foo___ZIJxV"
+        );
     }
 
     #[test]
@@ -139,44 +212,58 @@ mod tests {
             Options {
                 context,
                 hash_prefix: "--".to_string(),
+                ..Default::default()
             },
         );
 
-        assert_eq!(generator.generate("foo", filepath()), "source__foo___QTVQp");
+        assert_eq!(
+            generator.generate("foo", filepath()).unwrap(),
+            "source__foo___QTVQp"
+        );
     }
 
     #[test]
     fn identity() {
         let generator = Generator::new("[local]");
 
-        assert_eq!(generator.generate("foo", filepath()), "foo");
+        assert_eq!(generator.generate("foo", filepath()).unwrap(), "foo");
     }
 
     #[test]
     fn leading_digit() {
         let generator = Generator::new("0[local]");
 
-        assert_eq!(generator.generate("foo", filepath()), "_0foo");
+        assert_eq!(generator.generate("foo", filepath()).unwrap(), "_0foo");
     }
 
     #[test]
     fn leading_digit_in_the_token() {
         let generator = Generator::new("[local]");
 
-        assert_eq!(generator.generate("0foo", filepath()), "_0foo");
+        assert_eq!(generator.generate("0foo", filepath()).unwrap(), "_0foo");
     }
 
     #[test]
     fn leading_two_hyphens() {
         let generator = Generator::new("--[local]");
 
-        assert_eq!(generator.generate("foo", filepath()), "_--foo");
+        assert_eq!(generator.generate("foo", filepath()).unwrap(), "_--foo");
     }
 
     #[test]
     fn leading_hyphen_and_digit() {
         let generator = Generator::new("-0[local]");
 
-        assert_eq!(generator.generate("foo", filepath()), "_-0foo");
+        assert_eq!(generator.generate("foo", filepath()).unwrap(), "_-0foo");
+    }
+
+    #[test]
+    fn replaces_invalid_symbols() {
+        let generator = Generator::new("[local]");
+
+        assert_eq!(
+            generator.generate(r"sm\:hidden", filepath()).unwrap(),
+            "sm--hidden"
+        );
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,685 @@
+//! Ports of the local ident functions used by popular bundler setups.
+//!
+//! Each function mirrors the JavaScript implementation closely so that the
+//! generated class names match the ones emitted by the bundler.
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::path::{Path, PathBuf};
+
+use lightningcss::{
+    css_modules::{Config, Pattern},
+    printer::PrinterOptions,
+    stylesheet::{ParserOptions, StyleSheet},
+};
+use serde::Deserialize;
+use ts_rs::TS;
+
+use crate::{
+    generic_names::{escape_identifier, relative_path, Options},
+    loader_utils::{
+        hash::get_hash_digest,
+        interpolate::{
+            interpolate_name, reg_exp_captures, LoaderContext, Options as LoaderUtilsOptions,
+        },
+    },
+    Error,
+};
+
+/// The algorithm used to turn a local class name into a scoped one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, TS)]
+#[serde(rename_all = "kebab-case")]
+#[ts(export, export_to = "types.d.ts")]
+pub enum NamingPreset {
+    /// [generic-names](https://github.com/css-modules/generic-names/)
+    #[default]
+    GenericNames,
+    /// css-loader's default `getLocalIdent`
+    CssLoader,
+    /// Next.js `getCssModuleLocalIdent`
+    Next,
+    /// create-react-app `getCSSModuleLocalIdent` from react-dev-utils
+    Cra,
+    /// postcss-modules default `generateScopedName`, as used by Vite
+    PostcssModules,
+    /// lightningcss' own css modules naming, as used by Parcel and Vite with lightningcss,
+    /// the pattern only supports `[name]`, `[local]` and `[hash]`
+    Lightningcss,
+}
+
+impl NamingPreset {
+    /// Whether the preset hashes the contents of the stylesheet
+    pub fn requires_source(&self) -> bool {
+        matches!(self, NamingPreset::PostcssModules)
+    }
+
+    /// Whether the preset is given class names as written in the stylesheet, e.g. `sm\:hidden`,
+    /// like postcss-modules-scope does, css-loader unescapes them before calling `getLocalIdent`
+    pub fn escapes_local_name(&self) -> bool {
+        matches!(
+            self,
+            NamingPreset::GenericNames | NamingPreset::PostcssModules
+        )
+    }
+}
+
+/// Hashing options of css-loader, defaults match webpack's `output` defaults
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LocalIdentHashOptions {
+    pub salt: String,
+    pub function: String,
+    pub digest: String,
+    pub digest_length: usize,
+}
+
+impl Default for LocalIdentHashOptions {
+    fn default() -> Self {
+        Self {
+            salt: "".to_string(),
+            function: "md4".to_string(),
+            digest: "hex".to_string(),
+            digest_length: 20,
+        }
+    }
+}
+
+/// Port of css-loader's `escapeLocalIdent`, without the final css escaping
+fn escape_local_ident(local_ident: &str) -> String {
+    lazy_static! {
+        static ref INVALID_START: Regex = Regex::new(r"^((-?[0-9])|--)").unwrap();
+        static ref FILENAME_RESERVED: Regex = Regex::new(r#"[<>:"/\\|?*]"#).unwrap();
+        static ref CONTROL_CHARS: Regex = Regex::new(r"[\x00-\x1f\x{80}-\x{9f}]").unwrap();
+    }
+
+    let result = INVALID_START.replace(local_ident, "_$1");
+    let result = FILENAME_RESERVED.replace_all(&result, "-");
+    let result = CONTROL_CHARS.replace_all(&result, "-");
+
+    result.replace('.', "-")
+}
+
+/// Port of css-loader's `defaultGetLocalIdent`
+pub fn css_loader(
+    pattern: &str,
+    options: &Options,
+    local_name: &str,
+    filepath: PathBuf,
+) -> Result<String, Error> {
+    lazy_static! {
+        static ref HASH_REGEX: Regex = Regex::new(
+            r"(?i)\[(?:([^:\]]+):)?(hash|contenthash|fullhash)(?::([a-z]+\d*))?(?::(\d+))?\]"
+        )
+        .unwrap();
+        static ref PATH_REGEX: Regex = Regex::new(r"(?i)\[\\*([\w:]+)\\*\]").unwrap();
+        static ref LEADING_DIGITS: Regex = Regex::new(r"^\d+").unwrap();
+        static ref NON_WORD: Regex = Regex::new(r"[^A-Za-z0-9_]+").unwrap();
+    }
+
+    let hash_options = &options.local_ident_hash;
+    let relative_resource_path = relative_path(&filepath, &options.context)?;
+    let content = format!("{}\x00{}", relative_resource_path, local_name);
+
+    let mut hash_function = hash_options.function.clone();
+    let mut hash_digest = hash_options.digest.clone();
+    let mut hash_digest_length = hash_options.digest_length;
+    let mut local_ident_name = pattern.to_string();
+
+    if let Some(caps) = HASH_REGEX.captures(pattern) {
+        let is_fullhash = caps[2].eq_ignore_ascii_case("fullhash");
+        if let Some(function) = caps.get(1) {
+            hash_function = function.as_str().to_string();
+        }
+        if let Some(digest) = caps.get(3) {
+            hash_digest = digest.as_str().to_string();
+        }
+        if let Some(length) = caps.get(4) {
+            hash_digest_length = length.as_str().parse().unwrap_or(hash_digest_length);
+        }
+
+        let replacement = if is_fullhash {
+            "[fullhash]"
+        } else {
+            "[contenthash]"
+        };
+        local_ident_name = HASH_REGEX
+            .replace_all(&local_ident_name, replacement)
+            .to_string();
+    }
+
+    let mut local_ident_hash = String::new();
+    let mut tier: u32 = 0;
+    while local_ident_hash.len() < hash_digest_length {
+        let mut data = hash_options.salt.as_bytes().to_vec();
+        data.extend_from_slice(&tier.to_le_bytes());
+        data.extend_from_slice(content.as_bytes());
+
+        let digest = get_hash_digest(&data, &hash_function, &hash_digest, usize::MAX)?;
+        if digest.is_empty() {
+            break;
+        }
+        let combined = format!("{}{}", local_ident_hash, digest);
+        let combined = LEADING_DIGITS.replace(&combined, "");
+        let combined = combined.replace('/', "_");
+        let combined = NON_WORD.replace_all(&combined, "");
+
+        local_ident_hash = combined.chars().take(hash_digest_length).collect();
+        tier += 1;
+    }
+
+    // webpack's `compilation.getPath` with `filename` and `chunk` data
+    let file = relative_resource_path;
+    let base = file.rsplit('/').next().unwrap_or_default();
+    let ext = Path::new(base)
+        .extension()
+        .map_or("".to_string(), |ext| format!(".{}", ext.to_string_lossy()));
+    let name = &base[..base.len() - ext.len()];
+    let path = &file[..file.len() - base.len()];
+
+    let mut result = PATH_REGEX
+        .replace_all(&local_ident_name, |caps: &Captures| -> String {
+            let matched = &caps[0];
+            let content = &caps[1];
+
+            if content.len() + 2 != matched.len() {
+                if matched.starts_with("[\\") && matched.ends_with("\\]") {
+                    return format!("[{}]", &matched[2..matched.len() - 2]);
+                }
+                return matched.to_string();
+            }
+
+            let (kind, arg) = match content.split_once(':') {
+                Some((kind, arg)) => (kind, Some(arg)),
+                None => (content, None),
+            };
+
+            match kind {
+                "file" => file.clone(),
+                "path" => path.to_string(),
+                "base" => base.to_string(),
+                "name" => name.to_string(),
+                "ext" => ext.clone(),
+                // getLocalIdent interpolates the path relative to the context, without query nor fragment
+                "query" | "fragment" => "".to_string(),
+                "hash" | "contenthash" | "chunkhash" => match arg.and_then(|a| a.parse().ok()) {
+                    Some(length) => local_ident_hash.chars().take(length).collect(),
+                    None => local_ident_hash.clone(),
+                },
+                _ => matched.to_string(),
+            }
+        })
+        .to_string();
+
+    if result.to_lowercase().contains("[folder]") {
+        let folder = css_loader_folder(&filepath, &options.context)?;
+        lazy_static! {
+            static ref FOLDER_REGEX: Regex = Regex::new(r"(?i)\[folder\]").unwrap();
+        }
+        result = FOLDER_REGEX
+            .replace_all(&result, folder.as_str())
+            .to_string();
+    }
+
+    if let Some(reg_exp) = &options.reg_exp {
+        let resource = filepath.to_string_lossy();
+        for (index, matched) in reg_exp_captures(reg_exp, &resource)?
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            result = result.replace(&format!("[{}]", index), &matched);
+        }
+    }
+
+    lazy_static! {
+        static ref LOCAL_REGEX: Regex = Regex::new(r"(?i)\[local\]").unwrap();
+    }
+
+    Ok(LOCAL_REGEX
+        .replace_all(&escape_local_ident(&result), regex::NoExpand(local_name))
+        .to_string())
+}
+
+fn css_loader_folder(filepath: &Path, context: &Path) -> Result<String, Error> {
+    let dirname = filepath.parent().unwrap_or(filepath);
+    let directory = relative_path(&dirname.join("_"), context)?;
+    let directory = &directory[..directory.len() - 1];
+
+    if directory.len() > 1 {
+        Ok(Path::new(directory)
+            .file_name()
+            .map_or("".to_string(), |folder| {
+                folder.to_string_lossy().to_string()
+            }))
+    } else {
+        Ok("".to_string())
+    }
+}
+
+/// Whether `relative_path` is an `index.module.*` file, which is named after its folder
+fn is_index_module(relative_path: &str, exclude_pages: bool) -> bool {
+    lazy_static! {
+        static ref INDEX_MODULE: Regex = Regex::new(r"index\.module\.(scss|sass|css)$").unwrap();
+    }
+
+    match INDEX_MODULE.find(relative_path) {
+        Some(found) => {
+            let before = &relative_path[..found.start()];
+            !(exclude_pages && (before.ends_with("pages/") || before.ends_with("pages\\")))
+        }
+        None => false,
+    }
+}
+
+/// Port of Next.js `getCssModuleLocalIdent`
+pub fn next(options: &Options, local_name: &str, filepath: PathBuf) -> Result<String, Error> {
+    lazy_static! {
+        static ref INVALID_SYMBOLS: Regex = Regex::new(r"[^a-zA-Z0-9-_]").unwrap();
+        static ref INVALID_START: Regex = Regex::new(r"^(\d|--|-\d)").unwrap();
+    }
+
+    let relative_path = relative_path(&filepath, &options.context)?;
+
+    let file_name_or_folder = if is_index_module(&relative_path, true) {
+        "[folder]"
+    } else {
+        "[name]"
+    };
+
+    let hash = get_hash_digest(
+        format!("filePath:{}#className:{}", relative_path, local_name).as_bytes(),
+        "sha1",
+        "base64",
+        5,
+    )?;
+
+    let name = interpolate_name(
+        LoaderContext {
+            resource_path: Some(filepath),
+            ..Default::default()
+        },
+        &format!("{}_{}__{}", file_name_or_folder, local_name, hash),
+        LoaderUtilsOptions {
+            context: Some(options.context.clone()),
+            content: None,
+            reg_exp: None,
+        },
+    )?;
+
+    let name = name.replacen(".module_", "_", 1);
+    let name = INVALID_SYMBOLS.replace_all(&name, "_");
+
+    Ok(INVALID_START.replace(&name, "__$1").into_owned())
+}
+
+/// Port of create-react-app `getCSSModuleLocalIdent`
+pub fn cra(options: &Options, local_name: &str, filepath: PathBuf) -> Result<String, Error> {
+    let relative_path = relative_path(&filepath, &options.context)?;
+
+    let file_name_or_folder = if is_index_module(&relative_path, false) {
+        "[folder]"
+    } else {
+        "[name]"
+    };
+
+    let hash = get_hash_digest(
+        format!("{}{}", relative_path, local_name).as_bytes(),
+        "md5",
+        "base64",
+        5,
+    )?;
+
+    let class_name = interpolate_name(
+        LoaderContext {
+            resource_path: Some(filepath),
+            ..Default::default()
+        },
+        &format!("{}_{}__{}", file_name_or_folder, local_name, hash),
+        LoaderUtilsOptions {
+            context: Some(options.context.clone()),
+            content: None,
+            reg_exp: None,
+        },
+    )?;
+
+    // the result of a custom getLocalIdent is escaped by css-loader
+    Ok(escape_local_ident(
+        &class_name.replacen(".module_", "_", 1).replace('.', "_"),
+    ))
+}
+
+/// Port of [string-hash](https://github.com/darkskyapp/string-hash)
+fn string_hash(source: &str) -> u32 {
+    let mut hash: i32 = 5381;
+    for code_unit in source.encode_utf16().collect::<Vec<u16>>().iter().rev() {
+        hash = hash.wrapping_mul(33) ^ (*code_unit as i32);
+    }
+
+    hash as u32
+}
+
+fn to_radix_36(mut value: u32) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    if value == 0 {
+        return "0".to_string();
+    }
+
+    let mut result = Vec::new();
+    while value > 0 {
+        result.push(DIGITS[(value % 36) as usize]);
+        value /= 36;
+    }
+    result.reverse();
+
+    String::from_utf8(result).unwrap()
+}
+
+/// Port of postcss-modules `generateScopedNameDefault`
+pub fn postcss_modules(local_name: &str, source: &str) -> String {
+    let index = source.find(&format!(".{}", local_name)).unwrap_or(0);
+    let line_number = source[..index]
+        .chars()
+        .filter(|c| *c == '\r' || *c == '\n')
+        .count()
+        + 1;
+    let hash: String = to_radix_36(string_hash(source)).chars().take(5).collect();
+
+    format!("_{}_{}_{}", local_name, hash, line_number)
+}
+
+/// Names `local_name` the way lightningcss does, by compiling a single class selector.
+///
+/// lightningcss hashes the path of the stylesheet relative to the project root,
+/// so the context is passed as `project_root`.
+pub fn lightningcss(
+    pattern: &str,
+    options: &Options,
+    local_name: &str,
+    filepath: PathBuf,
+) -> Result<String, Error> {
+    let css_pattern =
+        Pattern::parse(pattern).map_err(|err| Error::InvalidPattern(err.to_string()))?;
+
+    let source = format!(".{} {{}}", escape_identifier(local_name));
+    let stylesheet = StyleSheet::parse(
+        &source,
+        ParserOptions {
+            filename: filepath.to_string_lossy().to_string(),
+            css_modules: Some(Config {
+                pattern: css_pattern,
+                dashed_idents: false,
+            }),
+            ..ParserOptions::default()
+        },
+    );
+
+    let context = options.context.to_string_lossy().to_string();
+    let name = stylesheet
+        .ok()
+        .and_then(|stylesheet| {
+            stylesheet
+                .to_css(PrinterOptions {
+                    project_root: Some(&context),
+                    ..PrinterOptions::default()
+                })
+                .ok()
+        })
+        .and_then(|result| result.exports)
+        .and_then(|exports| exports.get(local_name).map(|export| export.name.clone()))
+        // names that are not a valid class selector are kept as is
+        .unwrap_or_else(|| local_name.to_string());
+
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        generic_names::{
+            presets::{LocalIdentHashOptions, NamingPreset},
+            Generator, Options,
+        },
+        Error,
+    };
+
+    // vectors were computed with the JavaScript implementations of each preset
+
+    fn options(preset: NamingPreset) -> Options {
+        Options {
+            context: PathBuf::from("/app"),
+            hash_prefix: "".to_string(),
+            preset,
+            local_ident_hash: LocalIdentHashOptions::default(),
+            reg_exp: None,
+            resource_query: "".to_string(),
+            resource_fragment: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn css_loader_default_pattern() {
+        let generator = Generator::new_with_options(
+            "[hash:base64]",
+            Options {
+                local_ident_hash: LocalIdentHashOptions {
+                    function: "md5".to_string(),
+                    ..Default::default()
+                },
+                ..options(NamingPreset::CssLoader)
+            },
+        );
+
+        assert_eq!(
+            generator
+                .generate("foo", "/app/src/button.module.css".into())
+                .unwrap(),
+            "SArPKA4qDIkEGC47Kh4v"
+        );
+    }
+
+    #[test]
+    fn css_loader_path_name_local() {
+        let generator = Generator::new_with_options(
+            "[path][name]__[local]--[hash:base64:5]",
+            Options {
+                local_ident_hash: LocalIdentHashOptions {
+                    function: "sha1".to_string(),
+                    salt: "salt".to_string(),
+                    ..Default::default()
+                },
+                ..options(NamingPreset::CssLoader)
+            },
+        );
+
+        assert_eq!(
+            generator
+                .generate("foo", "/app/src/components/button.module.css".into())
+                .unwrap(),
+            "src-components-button-module__foo--LHuT9"
+        );
+    }
+
+    #[test]
+    fn css_loader_folder_and_ext() {
+        let generator = Generator::new_with_options(
+            "[folder]_[name][ext]_[local]",
+            options(NamingPreset::CssLoader),
+        );
+
+        assert_eq!(
+            generator
+                .generate("foo", "/app/src/button.css".into())
+                .unwrap(),
+            "src_button-css_foo"
+        );
+    }
+
+    #[test]
+    fn css_loader_escaped_placeholder() {
+        let generator =
+            Generator::new_with_options("[\\name\\]_[local]", options(NamingPreset::CssLoader));
+
+        assert_eq!(
+            generator
+                .generate("foo", "/app/src/button.css".into())
+                .unwrap(),
+            "[name]_foo"
+        );
+    }
+
+    #[test]
+    fn css_loader_reg_exp() {
+        let generator = Generator::new_with_options(
+            "[1]_[name]__[local]",
+            Options {
+                reg_exp: Some(r"/app/src/(\w+)/".to_string()),
+                ..options(NamingPreset::CssLoader)
+            },
+        );
+
+        assert_eq!(
+            generator
+                .generate("foo", "/app/src/components/button.css".into())
+                .unwrap(),
+            "components_button__foo"
+        );
+    }
+
+    // the import is `/app/button.css?theme=dark#top`
+    #[test]
+    fn css_loader_ignores_query_and_fragment() {
+        let generator = Generator::new_with_options(
+            "[name]__[local][query][fragment]",
+            Options {
+                resource_query: "?theme=dark".to_string(),
+                resource_fragment: "#top".to_string(),
+                ..options(NamingPreset::CssLoader)
+            },
+        );
+
+        assert_eq!(
+            generator.generate("foo", "/app/button.css".into()).unwrap(),
+            "button__foo"
+        );
+    }
+
+    #[test]
+    fn css_loader_unsupported_hash_function() {
+        let generator = Generator::new_with_options(
+            "[hash:base64]",
+            Options {
+                local_ident_hash: LocalIdentHashOptions {
+                    function: "crc32".to_string(),
+                    ..Default::default()
+                },
+                ..options(NamingPreset::CssLoader)
+            },
+        );
+
+        assert_eq!(
+            generator.generate("foo", "/app/src/button.css".into()),
+            Err(Error::UnsupportedHashFunction("crc32".to_string()))
+        );
+    }
+
+    #[test]
+    fn next_named_file() {
+        let generator = Generator::new_with_options("", options(NamingPreset::Next));
+
+        assert_eq!(
+            generator
+                .generate("root", "/app/pages/about.module.css".into())
+                .unwrap(),
+            "about_root__dJSfA"
+        );
+    }
+
+    #[test]
+    fn next_index_file() {
+        let generator = Generator::new_with_options("", options(NamingPreset::Next));
+
+        assert_eq!(
+            generator
+                .generate("root", "/app/components/Card/index.module.css".into())
+                .unwrap(),
+            "Card_root__vFSqS"
+        );
+    }
+
+    #[test]
+    fn cra_named_file() {
+        let generator = Generator::new_with_options("", options(NamingPreset::Cra));
+
+        assert_eq!(
+            generator
+                .generate("title", "/app/src/App.module.css".into())
+                .unwrap(),
+            "App_title__YS7Xx"
+        );
+    }
+
+    #[test]
+    fn cra_index_file() {
+        let generator = Generator::new_with_options("", options(NamingPreset::Cra));
+
+        assert_eq!(
+            generator
+                .generate("title", "/app/src/Card/index.module.css".into())
+                .unwrap(),
+            "Card_title__9jhKT"
+        );
+    }
+
+    #[test]
+    fn lightningcss_hashes_the_project_relative_path() {
+        let generator =
+            Generator::new_with_options("[hash]_[local]", options(NamingPreset::Lightningcss));
+        let elsewhere = Generator::new_with_options(
+            "[hash]_[local]",
+            Options {
+                context: PathBuf::from("/elsewhere/app"),
+                ..options(NamingPreset::Lightningcss)
+            },
+        );
+
+        let name = generator
+            .generate("foo", "/app/src/a.module.css".into())
+            .unwrap();
+
+        assert!(name.ends_with("_foo"));
+        assert_eq!(
+            elsewhere
+                .generate("foo", "/elsewhere/app/src/a.module.css".into())
+                .unwrap(),
+            name
+        );
+    }
+
+    #[test]
+    fn lightningcss_name_segment() {
+        let generator =
+            Generator::new_with_options("[name]_[local]", options(NamingPreset::Lightningcss));
+
+        assert_eq!(
+            generator
+                .generate("foo", "/app/src/a.module.css".into())
+                .unwrap(),
+            "a-module_foo"
+        );
+    }
+
+    #[test]
+    fn postcss_modules_default() {
+        let generator = Generator::new_with_options("", options(NamingPreset::PostcssModules));
+        let source = ".a {\n  color: red;\n}\n\n.title {\n  color: blue;\n}\n";
+
+        assert_eq!(
+            generator
+                .generate_with_source("title", "/app/src/a.module.css".into(), source)
+                .unwrap(),
+            "_title_1qerk_5"
+        );
+    }
+}
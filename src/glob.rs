@@ -0,0 +1,138 @@
+use std::fmt;
+
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer};
+
+/// A minimal glob matcher used to select stylesheets by path.
+///
+/// Supported syntax:
+/// - `*` matches any sequence of characters except `/`
+/// - `**` matches any sequence of characters including `/`
+/// - `?` matches a single character except `/`
+/// - `{a,b}` matches either alternative
+///
+/// Everything else is matched literally.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: String,
+    regex: Regex,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut regex = String::from("^");
+        let mut chars = pattern.chars().peekable();
+        let mut depth = 0;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '*' => {
+                    if chars.peek() == Some(&'*') {
+                        chars.next();
+                        // `**/` also matches zero directories
+                        if chars.peek() == Some(&'/') {
+                            chars.next();
+                            regex.push_str("(?:.*/)?");
+                        } else {
+                            regex.push_str(".*");
+                        }
+                    } else {
+                        regex.push_str("[^/]*");
+                    }
+                }
+                '?' => regex.push_str("[^/]"),
+                '{' => {
+                    depth += 1;
+                    regex.push_str("(?:");
+                }
+                '}' if depth > 0 => {
+                    depth -= 1;
+                    regex.push(')');
+                }
+                ',' if depth > 0 => regex.push('|'),
+                _ => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+
+        if depth > 0 {
+            return Err(format!("Unclosed `{{` in glob {:?}", pattern));
+        }
+
+        regex.push('$');
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex: Regex::new(&regex).map_err(|err| err.to_string())?,
+        })
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        self.regex.is_match(path)
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+impl<'de> Deserialize<'de> for Glob {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Glob::new(&pattern).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::glob::Glob;
+
+    #[test]
+    fn single_star_does_not_cross_directories() {
+        let glob = Glob::new("src/*.css").unwrap();
+
+        assert!(glob.is_match("src/a.css"));
+        assert!(!glob.is_match("src/nested/a.css"));
+    }
+
+    #[test]
+    fn double_star_matches_any_depth() {
+        let glob = Glob::new("packages/legacy-ui/**").unwrap();
+
+        assert!(glob.is_match("packages/legacy-ui/button.css"));
+        assert!(glob.is_match("packages/legacy-ui/src/components/button.css"));
+        assert!(!glob.is_match("packages/app/button.css"));
+    }
+
+    #[test]
+    fn double_star_slash_matches_zero_directories() {
+        let glob = Glob::new("**/*.global.css").unwrap();
+
+        assert!(glob.is_match("reset.global.css"));
+        assert!(glob.is_match("src/styles/reset.global.css"));
+        assert!(!glob.is_match("src/styles/reset.css"));
+    }
+
+    #[test]
+    fn alternatives() {
+        let glob = Glob::new("src/{components,pages}/*.css").unwrap();
+
+        assert!(glob.is_match("src/components/a.css"));
+        assert!(glob.is_match("src/pages/a.css"));
+        assert!(!glob.is_match("src/utils/a.css"));
+    }
+
+    #[test]
+    fn special_characters_are_literal() {
+        let glob = Glob::new("src/a+b.(c).css").unwrap();
+
+        assert!(glob.is_match("src/a+b.(c).css"));
+        assert!(!glob.is_match("src/aab.(c).css"));
+    }
+
+    #[test]
+    fn unclosed_brace_is_an_error() {
+        assert!(Glob::new("src/{a,b.css").is_err());
+    }
+}
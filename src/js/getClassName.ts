@@ -6,9 +6,23 @@ export default (
     .split(" ")
     .map((styleNameValue) => {
       if (!styleNameValue) return "";
-      const moduleAndStyleName = styleNameValue.split(".");
-      const module = moduleAndStyleName[1] ? moduleAndStyleName[0] : "";
-      const styleName = moduleAndStyleName[1] || moduleAndStyleName[0];
+      // `:global(name)` and `global.name` are classes of global stylesheets, used as is,
+      // unless a stylesheet is imported as `global`
+      const globalName = /^(?::global\((.+)\)|global\.(.+))$/.exec(styleNameValue);
+      if (globalName && (globalName[1] || !("global" in styleModuleImportMap)))
+        return globalName[1] || globalName[2];
+      // escaped class names such as `mt-2.5` are only split when the prefix is an import
+      const dot = styleNameValue.indexOf(".");
+      const isPrefixed =
+        dot !== -1 &&
+        (styleNameValue.slice(0, dot) in styleModuleImportMap ||
+          !Object.keys(styleModuleImportMap).some(
+            (key) => styleModuleImportMap[key][styleNameValue]
+          ));
+      const module = isPrefixed ? styleNameValue.slice(0, dot) : "";
+      const styleName = isPrefixed
+        ? styleNameValue.slice(dot + 1)
+        : styleNameValue;
       if (!module) {
         if (
           styleModuleImportMap[module] &&
@@ -0,0 +1,20 @@
+export type ExportKind =
+  | "class"
+  | "id"
+  | "keyframes"
+  | "custom-ident"
+  | "dashed-ident"
+  | "global-class"
+  | "value";
+
+/**
+ * Returns the scoped name of `name`, e.g. an id or a keyframes name, optionally checking its kind.
+ * Calls are replaced at compile time by the swc plugin, this is only reached without it.
+ */
+export const scopedName = (name: string, kind?: ExportKind): string => {
+  throw new Error(
+    `scopedName(${JSON.stringify(name)}${
+      kind ? `, ${JSON.stringify(kind)}` : ""
+    }) must be compiled by swc-plugin-react-css-modules`
+  );
+};
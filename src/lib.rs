@@ -1,16 +1,30 @@
+pub mod css_exports;
 pub mod generic_names;
 pub mod loader_utils;
 
 mod auto_map_css_module;
+mod cache;
 mod config;
+mod css_values;
+mod diagnostics;
+mod error;
+mod glob;
+mod manifest;
 mod process_stylesheet;
 
-pub use config::Config;
 pub use auto_map_css_module::AutoMapCssModules;
-use swc_core::{ecma::{
-    ast::Program, visit::{as_folder, FoldWith}
-}, plugin::metadata::TransformPluginMetadataContextKind};
+pub use config::{ClassMapSource, Config, CssParserConfig, NamingOverride, RuntimeFallback};
+pub use diagnostics::{DiagnosticCode, Severity};
+pub use error::{CssLocation, Error};
 use swc_core::plugin::{plugin_transform, proxies::TransformPluginProgramMetadata};
+use swc_core::{
+    common::DUMMY_SP,
+    ecma::{
+        ast::Program,
+        visit::{as_folder, FoldWith},
+    },
+    plugin::metadata::TransformPluginMetadataContextKind,
+};
 
 /// An example plugin function with macro support.
 /// `plugin_transform` macro interop pointers into deserialized structs, as well
@@ -29,26 +43,36 @@ use swc_core::plugin::{plugin_transform, proxies::TransformPluginProgramMetadata
 /// Refer swc_plugin_macro to see how does it work internally.
 #[plugin_transform]
 pub fn process_transform(program: Program, metadata: TransformPluginProgramMetadata) -> Program {
-    let config: Config = serde_json::from_str(
+    let config = serde_json::from_str::<Config>(
         &metadata
             .get_transform_plugin_config()
-            .expect("failed to get plugin config"),
+            .unwrap_or_else(|| "{}".to_string()),
     )
-    .expect("invalid config");
+    .map_err(|err| Error::InvalidConfig(err.to_string()));
 
     let filepath = metadata
         .get_context(&TransformPluginMetadataContextKind::Filename)
-        .expect("failed to get filepath");
+        .ok_or(Error::MissingMetadata("filepath"));
 
     let cwd = metadata
         .get_context(&TransformPluginMetadataContextKind::Cwd)
-        .expect("failed to get cwd");
+        .ok_or(Error::MissingMetadata("cwd"));
 
-    program.fold_with(&mut as_folder(AutoMapCssModules::new(
-        cwd.as_str(),
-        filepath.as_str(),
-        config,
-    )))
+    match (config, filepath, cwd) {
+        (Ok(config), Ok(filepath), Ok(cwd)) => program.fold_with(&mut as_folder(
+            AutoMapCssModules::new(cwd.as_str(), filepath.as_str(), config),
+        )),
+        (config, filepath, cwd) => {
+            // without these the styleName attributes cannot be resolved, the program is left as is
+            for err in [config.err(), filepath.err(), cwd.err()]
+                .into_iter()
+                .flatten()
+            {
+                diagnostics::report(DUMMY_SP, &err, err.code().default_severity());
+            }
+            program
+        }
+    }
 }
 
 // An example to test plugin transform.
@@ -60,4 +84,4 @@ pub fn process_transform(program: Program, metadata: TransformPluginProgramMetad
 //     |_| as_folder(AutoMapCssModules::new(env::current_dir().unwrap().to_str().unwrap(), "src/lib.rs")),
 //     boo,
 //     r#"foo === bar;"#
-// );
\ No newline at end of file
+// );
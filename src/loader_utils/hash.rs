@@ -5,6 +5,8 @@ use base64ct::Encoding as Base64Encoding;
 use digest::DynDigest;
 use std::hash::Hasher;
 
+use crate::Error;
+
 /// A Rust versions of [getHashDigest](https://github.com/webpack/loader-utils#gethashdigest).
 ///
 /// # Arguments
@@ -18,24 +20,24 @@ pub fn get_hash_digest(
     algorithm: &str,
     digest_type: &str,
     max_length: usize,
-) -> String {
-    let hash = use_hasher(algorithm, data);
-    let encoded = use_digest(digest_type, hash);
+) -> Result<String, Error> {
+    let hash = use_hasher(algorithm, data)?;
+    let encoded = use_digest(digest_type, hash)?;
     let result = encoded.get(0..max_length).unwrap_or(encoded.as_str());
 
-    result.to_string()
+    Ok(result.to_string())
 }
 
-fn use_hasher(hash_type: &str, data: &[u8]) -> Box<[u8]> {
+fn use_hasher(hash_type: &str, data: &[u8]) -> Result<Box<[u8]>, Error> {
     // TODO: rewrite to DynDigest
     if hash_type == "xxhash64" {
-        return xxhash64(data);
+        return Ok(xxhash64(data));
     }
 
-    let mut hasher = select_hasher(hash_type);
+    let mut hasher = select_hasher(hash_type)?;
     hasher.update(data);
 
-    hasher.finalize_reset()
+    Ok(hasher.finalize_reset())
 }
 
 fn xxhash64(data: &[u8]) -> Box<[u8]> {
@@ -46,8 +48,8 @@ fn xxhash64(data: &[u8]) -> Box<[u8]> {
 }
 
 #[allow(clippy::box_default)]
-fn select_hasher(hash_type: &str) -> Box<dyn DynDigest> {
-    match hash_type {
+fn select_hasher(hash_type: &str) -> Result<Box<dyn DynDigest>, Error> {
+    Ok(match hash_type {
         "md4" => Box::new(md4::Md4::default()),
         "md5" => Box::new(md5::Md5::default()),
         "sha1" => Box::new(sha1::Sha1::default()),
@@ -55,16 +57,16 @@ fn select_hasher(hash_type: &str) -> Box<dyn DynDigest> {
         "sha256" => Box::new(sha2::Sha256::default()),
         "sha384" => Box::new(sha2::Sha384::default()),
         "sha512" => Box::new(sha2::Sha512::default()),
-        _ => unimplemented!("unsupported hash function: {}", hash_type),
-    }
+        _ => return Err(Error::UnsupportedHashFunction(hash_type.to_string())),
+    })
 }
 
-fn use_digest(digest_type: &str, input: Box<[u8]>) -> String {
-    match digest_type {
+fn use_digest(digest_type: &str, input: Box<[u8]>) -> Result<String, Error> {
+    Ok(match digest_type {
         "base32" => Base32::encode_string(&input),
         "base64" => Base64::encode_string(&input),
         "base64url" => Base64Url::encode_string(&input),
         "hex" => base16ct::lower::encode_string(&input),
-        _ => unimplemented!("unsupported hash digest: {}", digest_type),
-    }
+        _ => return Err(Error::UnsupportedHashDigest(digest_type.to_string())),
+    })
 }
\ No newline at end of file
@@ -1,16 +1,24 @@
 use lazy_static::lazy_static;
 use regex::{Captures, Regex};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::loader_utils::hash::get_hash_digest;
+use crate::{loader_utils::hash::get_hash_digest, Error};
 
+#[derive(Debug, Default)]
 pub struct LoaderContext {
     pub resource_path: Option<PathBuf>,
+    /// `?query` of the resource, may end with the `#fragment`
+    pub resource_query: Option<String>,
+    /// `#fragment` of the resource
+    pub resource_fragment: Option<String>,
 }
 
+#[derive(Debug, Default)]
 pub struct Options<'a> {
     pub context: Option<PathBuf>,
     pub content: Option<&'a [u8]>,
+    /// matched against the resource path, `[N]` is replaced by the N-th capture group
+    pub reg_exp: Option<&'a str>,
 }
 
 /// A Rust versions of [interpolateName](https://github.com/webpack/loader-utils#interpolatename).
@@ -18,14 +26,20 @@ pub struct Options<'a> {
 /// The following tokens are replaced in the name parameter:
 /// - `[ext]` the extension of the resource
 /// - `[name]` the basename of the resource
+/// - `[path]` the path of the resource relative to the `context` option
 /// - `[folder]` the folder the resource is in
+/// - `[query]` the query of the resource, i.e. `?foo=bar`
+/// - `[fragment]` the fragment of the resource, i.e. `#hash`
 /// - `[contenthash]` the hash of options.content (Buffer) (by default it's the hex digest of the xxhash64 hash)
 /// - `[<hashType>:contenthash:<digestType>:<length>]` optionally one can configure
 ///   other hashTypes, i. e. xxhash64, sha1, md4, md5, sha256, sha512
 ///   other digestTypes, i. e. hex, base32, base64
 ///   and length the length in chars
 /// - `[hash]` the hash of options.content (Buffer) (by default it's the hex digest of the xxhash64 hash)
+/// - `[N]` the N-th match obtained from matching the resource path against the `reg_exp` option
+///
 /// In loader context `[hash]` and `[contenthash]` are the same, but we recommend using `[contenthash]` for avoid misleading.
+/// As in webpack, a token written `[\name\]` is not replaced, it becomes `[name]`.
 ///
 /// # Examples
 ///
@@ -35,11 +49,13 @@ pub struct Options<'a> {
 ///
 /// let loader_context = LoaderContext {
 ///     resource_path: Some("/absolute/path/to/app/js/javascript.js".into()),
+///     ..Default::default()
 /// };
 ///
 /// let options = Options {
 ///     context: Some(PathBuf::from("/absolute/path/to/app")),
 ///     content: Some(b"content"),
+///     ..Default::default()
 /// };
 ///
 /// assert_eq!(
@@ -48,57 +64,444 @@ pub struct Options<'a> {
 ///         "js/[hash].script.[ext]",
 ///         options,
 ///     ),
-///     "js/6c5b191a31c5a9fc.script.js".to_string(),
+///     Ok("js/6c5b191a31c5a9fc.script.js".to_string()),
 /// );
 /// ```
-pub fn interpolate_name(loader_context: LoaderContext, pattern: &str, options: Options) -> String {
+pub fn interpolate_name(
+    loader_context: LoaderContext,
+    pattern: &str,
+    options: Options,
+) -> Result<String, Error> {
     let mut url = pattern.to_string();
 
-    let mut ext = "bin";
-    let mut name = "file";
-    let mut folder = "";
-    // TODO: support path
-    // TODO: support query
+    let mut ext = "bin".to_string();
+    let mut basename = "file".to_string();
+    let mut directory = "".to_string();
+    let mut folder = "".to_string();
+    let mut query = "".to_string();
+    let mut fragment = "".to_string();
 
     if let Some(resource_path) = &loader_context.resource_path {
-        if let Some(extension) = resource_path.extension() {
-            ext = extension.to_str().unwrap();
-        }
+        let resource = resource_path.to_string_lossy().replace('\\', "/");
+        let (dir, base) = match resource.rfind('/') {
+            Some(0) => ("/", &resource[1..]),
+            Some(index) => (&resource[..index], &resource[index + 1..]),
+            None => ("", resource.as_str()),
+        };
+        // like node's `path.parse`, a leading dot does not start the extension
+        let name = match base.rfind('.') {
+            Some(index) if index > 0 => {
+                ext = base[index + 1..].to_string();
+                &base[..index]
+            }
+            _ => base,
+        };
 
-        if let Some(file_name) = resource_path.file_stem() {
-            name = file_name.to_str().unwrap();
+        let mut resource_dir = resource.clone();
+        if !dir.is_empty() {
+            basename = name.to_string();
+            resource_dir = format!("{}/", dir);
         }
 
-        if let Some(dir) = resource_path.parent().unwrap().file_name() {
-            folder = dir.to_str().unwrap();
+        directory = match &options.context {
+            Some(context) => {
+                let relative = relative_path(&format!("{}_", resource_dir), context);
+                let relative = replace_parent_dirs(&relative);
+                relative[..relative.len() - 1].to_string()
+            }
+            None => replace_parent_dirs(&resource_dir),
+        };
+
+        if directory.len() == 1 {
+            directory = "".to_string();
+        } else if directory.len() > 1 {
+            folder = directory
+                .trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string();
         }
     }
 
+    if let Some(resource_query) = loader_context.resource_query.filter(|q| q.len() > 1) {
+        let (before, after) = match resource_query.find('#') {
+            Some(index) => resource_query.split_at(index),
+            None => (resource_query.as_str(), ""),
+        };
+        query = before.to_string();
+        fragment = after.to_string();
+    }
+    if let Some(resource_fragment) = loader_context.resource_fragment.filter(|f| f.len() > 1) {
+        fragment = resource_fragment;
+    }
+
     if let Some(data) = options.content {
         lazy_static! {
             static ref HASH_REGEX: Regex = Regex::new(
-                r"\[(?:([^\[:\]]+):)?(?:hash|contenthash)(?::([a-z]+\d*))?(?::(\d+))?\]"
+                r"(?i)\[(?:([^\[:\]]+):)?(?:hash|contenthash)(?::([a-z]+\d*))?(?::(\d+))?\]"
             )
             .unwrap();
         }
 
+        let mut error = None;
         url = HASH_REGEX
             .replace_all(&url, |caps: &Captures| -> String {
                 let algorithm = caps.get(1).map_or("xxhash64", |m| m.as_str());
                 let digest_type = caps.get(2).map_or("hex", |m| m.as_str());
-                let max_length = caps
-                    .get(3)
-                    .map_or(9999, |m: regex::Match<'_>| m.as_str().parse().unwrap());
+                let max_length = caps.get(3).map_or(9999, |m: regex::Match<'_>| {
+                    m.as_str().parse().unwrap_or(9999)
+                });
 
-                get_hash_digest(data, algorithm, digest_type, max_length)
+                get_hash_digest(data, algorithm, digest_type, max_length).unwrap_or_else(|err| {
+                    error = Some(err);
+                    String::new()
+                })
             })
             .to_string();
+
+        if let Some(err) = error {
+            return Err(err);
+        }
+    }
+
+    let captures = match (options.reg_exp, &loader_context.resource_path) {
+        (Some(reg_exp), Some(resource_path)) => {
+            reg_exp_captures(reg_exp, &resource_path.to_string_lossy())?
+        }
+        _ => None,
+    };
+
+    lazy_static! {
+        static ref TOKEN_REGEX: Regex = Regex::new(r"\[\\*([\w:]+)\\*\]").unwrap();
+    }
+
+    url = TOKEN_REGEX
+        .replace_all(&url, |caps: &Captures| -> String {
+            let matched = &caps[0];
+            let content = &caps[1];
+
+            if content.len() + 2 != matched.len() {
+                if matched.starts_with("[\\") && matched.ends_with("\\]") {
+                    return format!("[{}]", &matched[2..matched.len() - 2]);
+                }
+                return matched.to_string();
+            }
+
+            match content.to_lowercase().as_str() {
+                "ext" => ext.clone(),
+                "name" => basename.clone(),
+                "path" => directory.clone(),
+                "folder" => folder.clone(),
+                "query" => query.clone(),
+                "fragment" => fragment.clone(),
+                index => index
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| captures.as_ref()?.get(index).cloned())
+                    .unwrap_or_else(|| matched.to_string()),
+            }
+        })
+        .to_string();
+
+    Ok(url)
+}
+
+/// Matches `reg_exp` against `resource`, an unmatched group is `undefined` as in JavaScript.
+/// Returns `None` if it does not match.
+pub(crate) fn reg_exp_captures(
+    reg_exp: &str,
+    resource: &str,
+) -> Result<Option<Vec<String>>, Error> {
+    let regex = Regex::new(reg_exp)
+        .map_err(|err| Error::InvalidPattern(format!("Invalid reg_exp {:?}: {}", reg_exp, err)))?;
+
+    Ok(regex.captures(resource).map(|caps| {
+        caps.iter()
+            .map(|group| {
+                group
+                    .map_or("undefined", |group| group.as_str())
+                    .to_string()
+            })
+            .collect()
+    }))
+}
+
+/// Splits `resource` into its path, `?query` and `#fragment`, like webpack's `parseResource`
+pub(crate) fn parse_resource(resource: &str) -> (&str, &str, &str) {
+    let (rest, fragment) = match resource.find('#') {
+        Some(index) => resource.split_at(index),
+        None => (resource, ""),
+    };
+    match rest.find('?') {
+        Some(index) => {
+            let (path, query) = rest.split_at(index);
+            (path, query, fragment)
+        }
+        None => (rest, "", fragment),
+    }
+}
+
+/// Like node's `path.relative`, with forward slashes
+fn relative_path(path: &str, context: &Path) -> String {
+    pathdiff::diff_paths(path, context)
+        .map_or(path.to_string(), |path| path.to_string_lossy().to_string())
+        .replace('\\', "/")
+}
+
+/// `../` becomes `_/`, so that `[path]` stays inside the output directory
+fn replace_parent_dirs(path: &str) -> String {
+    lazy_static! {
+        static ref PARENT_DIR: Regex = Regex::new(r"\.\.(/)?").unwrap();
+    }
+
+    PARENT_DIR.replace_all(path, "_$1").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{interpolate_name, parse_resource, LoaderContext, Options};
+    use crate::Error;
+
+    // cases ported from loader-utils' interpolateName tests
+
+    fn resource(path: &str) -> LoaderContext {
+        LoaderContext {
+            resource_path: Some(PathBuf::from(path)),
+            ..Default::default()
+        }
+    }
+
+    fn interpolate(loader_context: LoaderContext, pattern: &str, options: Options) -> String {
+        interpolate_name(loader_context, pattern, options).unwrap()
     }
 
-    url = url
-        .replace("[ext]", ext)
-        .replace("[name]", name)
-        .replace("[folder]", folder);
+    #[test]
+    fn interpolates_hashes_and_names() {
+        let cases = [
+            (
+                "/app/js/javascript.js",
+                "js/[hash].script.[ext]",
+                "js/0e6882304e9adbd5.script.js",
+            ),
+            ("/app/page.html", "html-[hash:6].html", "html-0e6882.html"),
+            ("/app/flash.txt", "[hash]", "0e6882304e9adbd5"),
+            (
+                "/app/img/image.png",
+                "[sha512:hash:base64:7].[ext]",
+                "DL9MrvO.png",
+            ),
+            (
+                "/app/dir/file.png",
+                "[path][name].[ext]?[hash]",
+                "/app/dir/file.png?0e6882304e9adbd5",
+            ),
+            (
+                "/pathWith.period/filename.js",
+                "js/[name].[ext]",
+                "js/filename.js",
+            ),
+            (
+                "/pathWith.period/filenameWithoutExt",
+                "js/[name].[ext]",
+                "js/filenameWithoutExt.bin",
+            ),
+            (
+                "/lib/components/modal/modal.css",
+                "[name]__modalTitle___[sha1:hash:hex:4]",
+                "modal__modalTitle___1eeb",
+            ),
+            (
+                "/lib/components/modal/modal.css",
+                "[name].[md5:hash:base64:20].[ext]",
+                "modal.lHP90NiApDwht3eNNIch.css",
+            ),
+            (
+                "/lib/components/modal/modal.css",
+                "[name].[md5:contenthash:base64:20].[ext]",
+                "modal.lHP90NiApDwht3eNNIch.css",
+            ),
+            // not a hash without `hash` or `contenthash`
+            (
+                "/lib/components/modal/modal.css",
+                "[name].[md5::base64:20].[ext]",
+                "modal.[md5::base64:20].css",
+            ),
+        ];
 
-    url
-}
\ No newline at end of file
+        for (path, pattern, expected) in cases {
+            let options = Options {
+                content: Some(b"test content"),
+                ..Default::default()
+            };
+            assert_eq!(
+                interpolate(resource(path), pattern, options),
+                expected,
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn interpolates_without_resource() {
+        for (pattern, expected) in [
+            ("[ext]", "bin"),
+            ("[name]", "file"),
+            ("[path]", ""),
+            ("[folder]", ""),
+        ] {
+            assert_eq!(
+                interpolate(LoaderContext::default(), pattern, Options::default()),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn interpolates_resource_tokens() {
+        for (pattern, expected) in [
+            ("[ext]", "exe"),
+            ("[name]", "file"),
+            ("[path]", "/path/to/"),
+            ("[folder]", "to"),
+        ] {
+            assert_eq!(
+                interpolate(resource("/path/to/file.exe"), pattern, Options::default()),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn interpolates_path_relative_to_context() {
+        let in_context = |path: &str, pattern: &str| {
+            let options = Options {
+                context: Some(PathBuf::from("/app")),
+                ..Default::default()
+            };
+            interpolate(resource(path), pattern, options)
+        };
+
+        assert_eq!(
+            in_context("/app/src/button.css", "[path][name].[ext]"),
+            "src/button.css"
+        );
+        assert_eq!(in_context("/app/src/button.css", "[folder]"), "src");
+        assert_eq!(in_context("/app/button.css", "[path][name]"), "button");
+        assert_eq!(in_context("/app/button.css", "[folder]"), "");
+        assert_eq!(
+            in_context("/lib/button.css", "[path][name]"),
+            "_/lib/button"
+        );
+    }
+
+    #[test]
+    fn interpolates_query_and_fragment() {
+        let with_query = |query: &str, fragment: Option<&str>| LoaderContext {
+            resource_path: Some(PathBuf::from("/app/file.css")),
+            resource_query: Some(query.to_string()),
+            resource_fragment: fragment.map(str::to_string),
+        };
+
+        assert_eq!(
+            interpolate(
+                with_query("?query=a%26b", None),
+                "[name][query]",
+                Options::default()
+            ),
+            "file?query=a%26b"
+        );
+        assert_eq!(
+            interpolate(
+                with_query("?query=a%26b#hash", None),
+                "[query][fragment]",
+                Options::default()
+            ),
+            "?query=a%26b#hash"
+        );
+        assert_eq!(
+            interpolate(
+                with_query("?query", Some("#top")),
+                "[name][fragment]",
+                Options::default()
+            ),
+            "file#top"
+        );
+        assert_eq!(
+            interpolate(with_query("?", None), "[name][query]", Options::default()),
+            "file"
+        );
+    }
+
+    #[test]
+    fn interpolates_reg_exp_captures() {
+        let options = |reg_exp| Options {
+            reg_exp: Some(reg_exp),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            interpolate(
+                resource("/app/img/image.png"),
+                "[1]-[2]-[name].[ext]",
+                options(r"/app/(\w+)/(\w+)\.png")
+            ),
+            "img-image-image.png"
+        );
+        assert_eq!(
+            interpolate(resource("/app/img/image.png"), "[0]", options(r"img/.*")),
+            "img/image.png"
+        );
+        assert_eq!(
+            interpolate(
+                resource("/app/image.png"),
+                "[1]",
+                options(r"/app/(img/)?image")
+            ),
+            "undefined"
+        );
+        assert_eq!(
+            interpolate(resource("/app/image.png"), "[1][3]", options(r"/lib/(.*)")),
+            "[1][3]"
+        );
+        assert!(matches!(
+            interpolate_name(resource("/app/image.png"), "[1]", options("(")),
+            Err(Error::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn parses_resources() {
+        assert_eq!(parse_resource("./a.css"), ("./a.css", "", ""));
+        assert_eq!(
+            parse_resource("./a.css?theme=dark"),
+            ("./a.css", "?theme=dark", "")
+        );
+        assert_eq!(parse_resource("./a.css#top"), ("./a.css", "", "#top"));
+        assert_eq!(parse_resource("./a.css?a#b?c"), ("./a.css", "?a", "#b?c"));
+    }
+
+    #[test]
+    fn keeps_escaped_tokens() {
+        let options = || Options {
+            content: Some(b"test content"),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            interpolate(resource("/app/file.css"), r"[\name\]-[name]", options()),
+            "[name]-file"
+        );
+        assert_eq!(
+            interpolate(resource("/app/file.css"), r"[\hash\]-[hash:6]", options()),
+            "[hash]-0e6882"
+        );
+        assert_eq!(
+            interpolate(resource("/app/file.css"), r"[\name]-[NAME]", options()),
+            r"[\name]-file"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use crate::{
+    cache::Fingerprint, css_exports::ExportKind, process_stylesheet::ParsedStylesheet, Error,
+};
+
+/// Class maps keyed by the stylesheet path relative to `root`, see `Config::class_map_source`
+pub type Manifest = HashMap<String, HashMap<String, String>>;
+
+/// Manifests read by this process, reloaded when they change
+static MANIFESTS: OnceLock<Mutex<HashMap<PathBuf, ManifestEntry>>> = OnceLock::new();
+
+struct ManifestEntry {
+    fingerprint: Fingerprint,
+    manifest: Arc<Manifest>,
+}
+
+/// Reads the manifest at `fs_path`, `full_path` is the path reported in errors
+pub fn load(fs_path: &Path, full_path: &Path) -> Result<Arc<Manifest>, Error> {
+    let invalid = |reason: String| Error::InvalidManifest {
+        path: full_path.to_path_buf(),
+        reason,
+    };
+    let fingerprint =
+        Fingerprint::of(fs_path).ok_or_else(|| invalid("the file can not be read".to_string()))?;
+
+    let mut manifests = MANIFESTS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+    if let Some(entry) = manifests.get(fs_path) {
+        if entry.fingerprint == fingerprint {
+            return Ok(entry.manifest.clone());
+        }
+    }
+
+    let contents =
+        fs::read_to_string(fs_path).map_err(|_| invalid("the file can not be read".to_string()))?;
+    let parsed: Manifest =
+        serde_json::from_str(&contents).map_err(|err| invalid(err.to_string()))?;
+    let manifest: Arc<Manifest> = Arc::new(
+        parsed
+            .into_iter()
+            .map(|(key, class_map)| (normalize_key(&key), class_map))
+            .collect(),
+    );
+
+    manifests.insert(
+        fs_path.to_path_buf(),
+        ManifestEntry {
+            fingerprint,
+            manifest: manifest.clone(),
+        },
+    );
+    Ok(manifest)
+}
+
+/// Key of the stylesheet at `full_path`, its path relative to `root` with forward slashes
+pub fn key_of(full_path: &Path, root: &Path) -> String {
+    let relative_path = pathdiff::diff_paths(full_path, root).unwrap_or(full_path.to_path_buf());
+    normalize_key(&relative_path.to_string_lossy())
+}
+
+/// Compares the class map of the manifest with the compiled one, sorted by name.
+/// `:global` classes are not exported by css-loader, they may be missing from the manifest.
+pub fn mismatches(
+    key: &str,
+    class_map: &HashMap<String, String>,
+    generated: &ParsedStylesheet,
+) -> Vec<Error> {
+    let names: BTreeSet<&String> = class_map
+        .keys()
+        .chain(generated.style_name_map.keys())
+        .collect();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let manifest = class_map.get(name);
+            let compiled = generated.style_name_map.get(name);
+            let is_global = generated
+                .kinds
+                .get(name)
+                .is_some_and(|kinds| *kinds == [ExportKind::GlobalClass]);
+            if manifest == compiled || (manifest.is_none() && is_global) {
+                return None;
+            }
+            Some(Error::ManifestMismatch {
+                key: key.to_string(),
+                name: name.clone(),
+                manifest: manifest.cloned(),
+                generated: compiled.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// `./src\a.css` and `src/a.css` refer to the same stylesheet
+fn normalize_key(key: &str) -> String {
+    let key = key.replace('\\', "/");
+    key.strip_prefix("./").unwrap_or(&key).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::Path};
+
+    use super::{key_of, mismatches, normalize_key};
+    use crate::{css_exports::ExportKind, process_stylesheet::ParsedStylesheet, Error};
+
+    fn class_map(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn normalizes_keys() {
+        assert_eq!(normalize_key("./src/a.module.css"), "src/a.module.css");
+        assert_eq!(normalize_key(r"src\a.module.css"), "src/a.module.css");
+        assert_eq!(
+            key_of(
+                Path::new("/project/src/a.module.css"),
+                Path::new("/project")
+            ),
+            "src/a.module.css"
+        );
+        assert_eq!(
+            key_of(Path::new("/shared/b.css"), Path::new("/project")),
+            "../shared/b.css"
+        );
+    }
+
+    #[test]
+    fn reports_mismatches() {
+        let generated = ParsedStylesheet {
+            style_name_map: class_map(&[("btn", "a_btn_y2"), ("icon", "a_icon_z3"), ("gl", "gl")]),
+            diagnostics: Vec::new(),
+            dependencies: Vec::new(),
+            kinds: HashMap::from([("gl".to_string(), vec![ExportKind::GlobalClass])]),
+        };
+        let manifest = class_map(&[
+            ("btn", "a_btn_x1"),
+            ("icon", "a_icon_z3"),
+            ("old", "a_old_w4"),
+        ]);
+
+        let mismatch =
+            |name: &str, manifest: Option<&str>, generated: Option<&str>| Error::ManifestMismatch {
+                key: "src/a.module.css".to_string(),
+                name: name.to_string(),
+                manifest: manifest.map(str::to_string),
+                generated: generated.map(str::to_string),
+            };
+        assert_eq!(
+            mismatches("src/a.module.css", &manifest, &generated),
+            vec![
+                mismatch("btn", Some("a_btn_x1"), Some("a_btn_y2")),
+                mismatch("old", Some("a_old_w4"), None),
+            ]
+        );
+    }
+}
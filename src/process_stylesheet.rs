@@ -1,126 +1,833 @@
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 
 use lightningcss::{
-    css_modules::{Config, CssModuleExport, CssModuleReference, Pattern},
+    css_modules::{
+        Config as CssModulesConfig, CssModuleExport, CssModuleExports, CssModuleReference, Pattern,
+    },
     printer::PrinterOptions,
-    stylesheet::{ParserOptions, StyleSheet, ToCssResult},
+    rules::{CssRule, Location},
+    stylesheet::{ParserFlags, ParserOptions, StyleSheet, ToCssResult},
     targets::Targets,
 };
 use path_absolutize::Absolutize;
 
-use crate::generic_names::{Generator, Options};
+use crate::{
+    auto_map_css_module::get_fs_path,
+    cache::{self, CacheKey, DiskCache, Fingerprint},
+    config::{CssModulesMode, ScopedNaming},
+    css_exports::{collect_exports_with_kinds, impure_selectors, ExportKind, ExportKinds},
+    css_values::{
+        extract_values, replace_class_aliases, replace_values, ValueSource, ValueStatement,
+    },
+    error::CssLocation,
+    generic_names::{escape_identifier, unescape_identifier, Generator, NamingPreset, Options},
+    Config, Error,
+};
 
-pub struct CssModuleParser {
-    /// the same pattern passed to genericNames
-    pattern: String,
-    /// root of project
-    context: PathBuf,
-    hash_prefix: String,
+/// Class map of a stylesheet, along with the problems found while building it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedStylesheet {
+    pub style_name_map: HashMap<String, String>,
+    /// reported at the import, e.g. the invalid css lightningcss recovered from
+    pub diagnostics: Vec<Error>,
+    /// other files read to build the map, i.e. the stylesheets imported and composed from
+    pub dependencies: Vec<PathBuf>,
+    /// what each name of `style_name_map` is used for, e.g. a class or keyframes
+    pub kinds: ExportKinds,
+}
+
+pub struct CssModuleParser<'a> {
+    config: &'a Config,
+    /// root of project, naming settings are resolved from it
+    root: &'a Path,
+    /// naming settings resolved for this stylesheet
+    naming: ScopedNaming,
+    /// css-loader `modules.mode` resolved for this stylesheet
+    mode: CssModulesMode,
     /// path to file that can actually be read
     /// works with virtualized fs
     fs_path: PathBuf,
     /// absolute path in the non-virtualized environment
     /// used to generate hash
     full_path: PathBuf,
+    /// results of previous builds, if `cache_dir` is set
+    disk_cache: Option<&'a DiskCache>,
+    /// full paths of the stylesheets importing or composing from this one, used to detect cycles
+    ancestors: Vec<PathBuf>,
 }
 
-impl CssModuleParser {
+/// A stylesheet referenced by `@import "specifier"` or `composes: ... from "specifier"`
+struct Dependency {
+    fs_path: PathBuf,
+    full_path: PathBuf,
+    /// `None` if it could not be resolved or parsed, which is already reported
+    parsed: Option<ParsedStylesheet>,
+}
+
+impl<'a> CssModuleParser<'a> {
     pub fn new(
-        pattern: String,
-        context: PathBuf,
-        hash_prefix: String,
+        config: &'a Config,
+        root: &'a Path,
         fs_path: PathBuf,
         full_path: PathBuf,
+        disk_cache: Option<&'a DiskCache>,
     ) -> Self {
         Self {
-            pattern,
-            context,
-            hash_prefix,
+            config,
+            root,
+            naming: config.scoped_naming_for(root, &full_path),
+            mode: config.mode_for(root, &full_path),
             fs_path,
             full_path,
+            disk_cache,
+            ancestors: Vec::new(),
         }
     }
 
-    pub fn generate_style_name_map(&self) -> Result<HashMap<String, String>, String> {
-        let file = fs::read_to_string(self.fs_path.clone());
-        let contents = match file {
-            Ok(data) => data,
-            Err(_) => return Err(format!("Could not read {:?}", self.full_path)),
+    /// Sets the `?query` and `#fragment` the stylesheet is imported with,
+    /// used by the `[query]` and `[fragment]` tokens
+    pub fn with_resource_query(mut self, query: &str, fragment: &str) -> Self {
+        self.naming.resource_query = query.to_string();
+        self.naming.resource_fragment = fragment.to_string();
+        self
+    }
+
+    /// Whether lightningcss names the classes itself
+    fn is_lightningcss_native(&self) -> bool {
+        self.naming.naming_preset == NamingPreset::Lightningcss
+    }
+
+    /// Returns the class map of the stylesheet, parsing it only if it is not cached yet
+    /// or changed since it was cached.
+    pub fn generate_style_name_map(&self) -> Result<ParsedStylesheet, Error> {
+        let fingerprint = Fingerprint::of(&self.fs_path).ok_or_else(|| Error::ReadStylesheet {
+            path: self.full_path.clone(),
+        })?;
+        let key = CacheKey {
+            fs_path: self.fs_path.clone(),
+            full_path: self.full_path.clone(),
+            naming: self.naming.clone(),
+            mode: self.mode,
+            parser: self.config.css_parser.clone(),
+            alias: self.config.alias.clone(),
         };
-        let stylesheet = StyleSheet::parse(
-            &contents,
+        if let Some(parsed) = cache::get(&key, &fingerprint) {
+            return Ok(parsed);
+        }
+
+        let contents =
+            fs::read_to_string(self.fs_path.clone()).map_err(|_| Error::ReadStylesheet {
+                path: self.full_path.clone(),
+            })?;
+        let stored = self
+            .disk_cache
+            .and_then(|disk_cache| disk_cache.load(&key, &contents));
+        let parsed = match stored {
+            Some(parsed) => parsed,
+            None => {
+                let parsed = self.parse_style_name_map(&contents)?;
+                if let Some(disk_cache) = self.disk_cache {
+                    disk_cache.store(&key, &contents, &parsed);
+                }
+                parsed
+            }
+        };
+        // names inside a cycle depend on the stylesheet it was entered from
+        let in_cycle = parsed.diagnostics.iter().any(|diagnostic| {
+            matches!(
+                diagnostic,
+                Error::ComposesCycle { .. } | Error::ImportCycle { .. }
+            )
+        });
+        if !in_cycle {
+            cache::insert(key, fingerprint, parsed.clone());
+        }
+        Ok(parsed)
+    }
+
+    fn parse_style_name_map(&self, contents: &str) -> Result<ParsedStylesheet, Error> {
+        let extracted = extract_values(contents).map_err(|offset| Error::ParseStylesheet {
+            path: self.full_path.clone(),
+            message: "Invalid @value declaration".to_string(),
+            location: Some(location_at(contents, offset)),
+        })?;
+        let mut diagnostics = Vec::new();
+        let values = self.resolve_values(extracted.statements, &mut diagnostics);
+        // locations point into the substituted source, lines are unchanged
+        let source = replace_values(
+            &replace_class_aliases(&extracted.source, &values.class_aliases),
+            &values.values,
+        );
+
+        let parser = &self.config.css_parser;
+        let mut flags = ParserFlags::empty();
+        flags.set(ParserFlags::NESTING, parser.nesting);
+        flags.set(ParserFlags::CUSTOM_MEDIA, parser.custom_media);
+        let warnings = Arc::new(RwLock::new(Vec::new()));
+
+        let pattern = if self.is_lightningcss_native() {
+            Pattern::parse(&self.naming.generate_scoped_name)
+                .map_err(|err| Error::InvalidPattern(err.to_string()))?
+        } else {
+            // not using lightning css hashing in favour of hashing via generic names
+            // lightning suggests that we do hashing ourselves https://github.com/parcel-bundler/lightningcss/issues/156#issuecomment-1131828962
+            Pattern::parse("[local]").unwrap()
+        };
+        let mut stylesheet = StyleSheet::parse(
+            &source,
             ParserOptions {
-                filename: self
-                    .full_path
-                    .clone()
-                    .into_os_string()
-                    .into_string()
-                    .unwrap(),
-                css_modules: Some(Config {
-                    // not using lightning css hashing in favour of hashing via generic names
-                    // lightning suggests that we do hashing ourselves https://github.com/parcel-bundler/lightningcss/issues/156#issuecomment-1131828962
-                    pattern: Pattern::parse("[local]").unwrap(),
-                    dashed_idents: false,
+                filename: self.full_path.to_string_lossy().to_string(),
+                css_modules: Some(CssModulesConfig {
+                    pattern,
+                    dashed_idents: parser.dashed_idents,
                 }),
+                flags,
+                error_recovery: parser.error_recovery,
+                warnings: Some(warnings.clone()),
                 ..ParserOptions::default()
             },
         )
-        .unwrap();
+        .map_err(|err| Error::ParseStylesheet {
+            path: self.full_path.clone(),
+            message: err.kind.to_string(),
+            location: css_location(&source, &err),
+        })?;
+
+        let imports: Vec<String> = stylesheet
+            .rules
+            .0
+            .iter()
+            .filter_map(|rule| match rule {
+                CssRule::Import(import) if !is_remote_url(&import.url) => {
+                    Some(import.url.to_string())
+                }
+                _ => None,
+            })
+            .collect();
+
+        // lightningcss only hashes names while printing, so native names need the printer
+        let (collected, mut own_kinds) =
+            collect_exports_with_kinds(&mut stylesheet, parser.dashed_idents);
+        let collected = if self.is_lightningcss_native() {
+            None
+        } else {
+            collected
+        };
+        let exports = match (self.mode, collected) {
+            // only the `:export` blocks are exported
+            (CssModulesMode::Icss, _) => {
+                own_kinds.clear();
+                CssModuleExports::new()
+            }
+            (_, Some(exports)) => exports,
+            (_, None) => self.print_exports(&stylesheet, &source)?,
+        };
+
+        if self.mode == CssModulesMode::Pure {
+            for impure in impure_selectors(&mut stylesheet) {
+                let (selector, location) = selector_at(&source, impure.loc, impure.index);
+                diagnostics.push(Error::ImpureSelector {
+                    path: self.full_path.clone(),
+                    selector,
+                    location: Some(location),
+                });
+            }
+        }
+
+        if let Ok(warnings) = warnings.read() {
+            diagnostics.extend(warnings.iter().map(|warning| Error::RecoveredStylesheet {
+                path: self.full_path.clone(),
+                message: warning.kind.to_string(),
+                location: css_location(&source, warning),
+            }));
+        }
+
+        let imported: Vec<Dependency> = imports
+            .iter()
+            .map(|specifier| {
+                self.load_dependency(
+                    specifier,
+                    |cycle| Error::ImportCycle { cycle },
+                    &mut diagnostics,
+                )
+            })
+            .collect();
+        let dependencies = self.load_dependencies(&exports, &mut diagnostics);
+        let generator = Generator::new_with_options(
+            &self.naming.generate_scoped_name,
+            Options {
+                context: self.naming.context.clone(),
+                hash_prefix: self.naming.hash_prefix.clone(),
+                preset: self.naming.naming_preset,
+                local_ident_hash: self.naming.local_ident_hash.clone(),
+                reg_exp: self.naming.reg_exp.clone(),
+                resource_query: self.naming.resource_query.clone(),
+                resource_fragment: self.naming.resource_fragment.clone(),
+            },
+        );
+        let mut resolver = ClassNameResolver {
+            parser: self,
+            exports: &exports,
+            dependencies: &dependencies,
+            generator: &generator,
+            source: contents,
+            resolved: HashMap::new(),
+            visiting: Vec::new(),
+            diagnostics,
+        };
+
+        // sorted so that diagnostics are reported in a stable order
+        let mut names: Vec<&String> = exports.keys().collect();
+        names.sort();
+        // like css-loader, classes of later imports win and the stylesheet's own classes win over all
+        let mut style_name_map = HashMap::new();
+        let mut kinds = ExportKinds::new();
+        for parsed in imported.iter().filter_map(|import| import.parsed.as_ref()) {
+            style_name_map.extend(parsed.style_name_map.clone());
+            kinds.extend(parsed.kinds.clone());
+        }
+        // `@value`s and `:export`s are exported along with the classes
+        for name in values.exports.keys() {
+            kinds.insert(name.clone(), vec![ExportKind::Value]);
+        }
+        style_name_map.extend(values.exports);
+        // an alias used as a selector stands for the imported classes, which are not globals
+        let mut imported_classes = HashSet::new();
+        for (alias, classes) in &values.class_aliases {
+            if !classes
+                .split_whitespace()
+                .all(|class| own_kinds.contains_key(class))
+            {
+                continue;
+            }
+            imported_classes.extend(classes.split_whitespace().map(str::to_string));
+            if !style_name_map.contains_key(alias) {
+                style_name_map.insert(alias.clone(), classes.clone());
+                kinds.insert(alias.clone(), vec![ExportKind::Class]);
+            }
+        }
+        let mut globals = Vec::new();
+        for (name, name_kinds) in own_kinds {
+            // `:global(.name)` only, the other definitions of the name win
+            if name_kinds == [ExportKind::GlobalClass] {
+                if style_name_map.contains_key(&name) || imported_classes.contains(&name) {
+                    continue;
+                }
+                globals.push(name.clone());
+            }
+            kinds.insert(name, name_kinds);
+        }
+        for name in names {
+            let class_names = resolver.resolve(name)?;
+            style_name_map.insert(name.clone(), class_names.join(" "));
+        }
+        // global classes are defined by the stylesheet too, they keep their name
+        for name in globals {
+            style_name_map.insert(name.clone(), name);
+        }
+        let diagnostics = resolver.diagnostics;
+
+        let mut dependency_paths = Vec::new();
+        let all_dependencies = imported
+            .iter()
+            .chain(&values.sources)
+            .chain(dependencies.values());
+        for dependency in all_dependencies {
+            dependency_paths.push(dependency.fs_path.clone());
+            if let Some(parsed) = &dependency.parsed {
+                dependency_paths.extend(parsed.dependencies.iter().cloned());
+            }
+        }
+        dependency_paths.sort();
+        dependency_paths.dedup();
 
+        Ok(ParsedStylesheet {
+            style_name_map,
+            diagnostics,
+            dependencies: dependency_paths,
+            kinds,
+        })
+    }
+
+    /// Resolves the `@value` declarations and ICSS blocks, in source order
+    fn resolve_values(
+        &self,
+        statements: Vec<ValueStatement>,
+        diagnostics: &mut Vec<Error>,
+    ) -> ResolvedValues {
+        let mut resolved = ResolvedValues::default();
+        let mut exports = Vec::new();
+
+        for statement in statements {
+            match statement {
+                // earlier values can be used in later ones
+                ValueStatement::Definition { name, value } => {
+                    let value = replace_values(&value, &resolved.values);
+                    resolved.values.insert(name.clone(), value.clone());
+                    resolved.exports.insert(name, value);
+                }
+                ValueStatement::Import { names, from } => {
+                    let specifier = match from {
+                        ValueSource::Path(path) => path,
+                        ValueSource::Value(name) => resolved
+                            .values
+                            .get(&name)
+                            .map_or(name, |path| path.trim_matches(['"', '\'']).to_string()),
+                    };
+                    for (name, value) in
+                        self.import_values(&specifier, names, &mut resolved, diagnostics)
+                    {
+                        resolved.values.insert(name.clone(), value.clone());
+                        resolved.exports.insert(name, value);
+                    }
+                }
+                // aliases are only exported through `:export`
+                ValueStatement::IcssImport { names, from } => {
+                    for (alias, value) in
+                        self.import_values(&from, names, &mut resolved, diagnostics)
+                    {
+                        resolved.class_aliases.insert(alias.clone(), value.clone());
+                        resolved.values.insert(alias, value);
+                    }
+                }
+                ValueStatement::Export { name, value } => exports.push((name, value)),
+            }
+        }
+
+        // `:export` blocks can use every import, wherever they are
+        for (name, value) in exports {
+            let value = replace_values(&value, &resolved.values);
+            resolved.exports.insert(name, value);
+        }
+        resolved
+    }
+
+    /// Returns the local names and values of `names` exported by the stylesheet `specifier` refers to
+    fn import_values(
+        &self,
+        specifier: &str,
+        names: Vec<(String, String)>,
+        resolved: &mut ResolvedValues,
+        diagnostics: &mut Vec<Error>,
+    ) -> Vec<(String, String)> {
+        let dependency =
+            self.load_dependency(specifier, |cycle| Error::ImportCycle { cycle }, diagnostics);
+        let mut values = Vec::new();
+        if let Some(parsed) = &dependency.parsed {
+            for (imported, local) in names {
+                match parsed.style_name_map.get(&imported) {
+                    Some(value) => values.push((local, value.clone())),
+                    None => push_unique(
+                        diagnostics,
+                        Error::UnresolvedValue {
+                            path: self.full_path.clone(),
+                            name: imported,
+                            specifier: specifier.to_string(),
+                            dependency: dependency.full_path.clone(),
+                        },
+                    ),
+                }
+            }
+        }
+        resolved.sources.push(dependency);
+        values
+    }
+
+    /// Prints the stylesheet only to collect its exports
+    fn print_exports(
+        &self,
+        stylesheet: &StyleSheet,
+        contents: &str,
+    ) -> Result<CssModuleExports, Error> {
         let css_result = stylesheet.to_css(PrinterOptions {
             minify: false,
             analyze_dependencies: None,
             pseudo_classes: None,
             source_map: None,
             targets: Targets::default(),
-            project_root: Some(&self.context.clone().into_os_string().into_string().unwrap()),
+            project_root: Some(&self.naming.context.to_string_lossy()),
         });
         match css_result {
-            Ok(ToCssResult { exports, .. }) => {
-                 match exports {
-                    Some(exports) =>  {
-                        let generator = Generator::new_with_options(
-                            &self.pattern,
-                            Options {
-                                context: self.context.clone(),
-                                hash_prefix: self.hash_prefix.clone(),
-                            },
-                        );
-                        Ok(exports
-                            .iter()
-                            .map(|(k, v)| (k.clone(), self.css_module_exports_to_str(&v, &generator)))
-                            .collect())
-                    },
-                    _ => Ok(HashMap::new())
-                 }
+            Ok(ToCssResult { exports, .. }) => Ok(exports.unwrap_or_default()),
+            Err(printer_err) => Err(Error::PrintStylesheet {
+                path: self.full_path.clone(),
+                message: printer_err.kind.to_string(),
+                location: css_location(contents, &printer_err),
+            }),
+        }
+    }
+
+    /// Parses every stylesheet composed from, keyed by specifier
+    fn load_dependencies(
+        &self,
+        exports: &CssModuleExports,
+        diagnostics: &mut Vec<Error>,
+    ) -> HashMap<String, Dependency> {
+        let mut specifiers: Vec<&String> = exports
+            .values()
+            .flat_map(|export| &export.composes)
+            .filter_map(|reference| match reference {
+                CssModuleReference::Dependency { specifier, .. } => Some(specifier),
+                _ => None,
+            })
+            .collect();
+        specifiers.sort();
+        specifiers.dedup();
+
+        specifiers
+            .into_iter()
+            .map(|specifier| {
+                let dependency = self.load_dependency(
+                    specifier,
+                    |cycle| Error::ComposesCycle { cycle },
+                    diagnostics,
+                );
+                (specifier.clone(), dependency)
+            })
+            .collect()
+    }
+
+    /// Parses the stylesheet `specifier` refers to, `cycle_error` is reported
+    /// if it already imports or composes from this one
+    fn load_dependency(
+        &self,
+        specifier: &str,
+        cycle_error: fn(Vec<PathBuf>) -> Error,
+        diagnostics: &mut Vec<Error>,
+    ) -> Dependency {
+        let (fs_path, full_path) = match self.resolve_dependency(specifier) {
+            Ok(paths) => paths,
+            Err(err) => {
+                push_unique(diagnostics, err);
+                return Dependency {
+                    fs_path: sibling_path(&self.fs_path, specifier),
+                    full_path: sibling_path(&self.full_path, specifier),
+                    parsed: None,
+                };
             }
-            Err(printer_err) => {
-                Err(printer_err.to_string())
+        };
+
+        let mut chain = self.ancestors.clone();
+        chain.push(self.full_path.clone());
+        if chain.contains(&full_path) {
+            chain.push(full_path.clone());
+            push_unique(diagnostics, cycle_error(chain));
+            return Dependency {
+                fs_path,
+                full_path,
+                parsed: None,
+            };
+        }
+
+        let parser = CssModuleParser {
+            ancestors: chain,
+            ..CssModuleParser::new(
+                self.config,
+                self.root,
+                fs_path.clone(),
+                full_path.clone(),
+                self.disk_cache,
+            )
+        };
+        let parsed = match parser.generate_style_name_map() {
+            Ok(parsed) => {
+                // problems of the stylesheets imported and composed from are shown at the import as well
+                for diagnostic in &parsed.diagnostics {
+                    push_unique(diagnostics, diagnostic.clone());
+                }
+                Some(parsed)
+            }
+            Err(err) => {
+                push_unique(diagnostics, err);
+                None
             }
+        };
+        Dependency {
+            fs_path,
+            full_path,
+            parsed,
         }
     }
 
-    fn css_module_exports_to_str(&self, export: &CssModuleExport, generator: &Generator) -> String {
-        format!(
-            "{} {}",
-            generator.generate(&export.name, self.full_path.clone()),
-            export
-                .composes
-                .iter()
-                .map(|reference| match reference {
-                    CssModuleReference::Local { name } =>
-                        generator.generate(name, self.full_path.clone()),
-                    // global compose need not be transformed
-                    CssModuleReference::Global { name } => name.clone(),
-                    CssModuleReference::Dependency { name, specifier } => {
-                        let path = self.full_path.clone().parent().unwrap().join(specifier);
-                        generator.generate(name, path.absolutize().unwrap().to_path_buf())
+    /// Returns the fs path and the full path of the stylesheet `specifier` refers to.
+    ///
+    /// Like css-loader, aliases are tried first, then the path relative to this stylesheet
+    /// and finally the `node_modules` directories above it.
+    /// Specifiers starting with `~` skip the relative path, `./` and `../` are only relative.
+    fn resolve_dependency(&self, specifier: &str) -> Result<(PathBuf, PathBuf), Error> {
+        let (request, is_module) = match specifier.strip_prefix('~') {
+            Some(request) => (request, true),
+            None => (specifier, false),
+        };
+        let unresolved = |path: PathBuf| Error::UnresolvedImport {
+            src: specifier.to_string(),
+            path,
+        };
+
+        if let Some(full_path) = self.config.resolve_alias(self.root, request) {
+            return match get_fs_path(self.root, full_path.clone()) {
+                Some(fs_path) => Ok((fs_path, full_path)),
+                None => Err(unresolved(full_path)),
+            };
+        }
+
+        // reported when nothing matches, the first path tried
+        let mut attempted = None;
+        if !is_module {
+            let relative = (
+                sibling_path(&self.fs_path, request),
+                sibling_path(&self.full_path, request),
+            );
+            if request.starts_with('.') || Path::new(request).has_root() || relative.0.is_file() {
+                return Ok(relative);
+            }
+            attempted = Some(relative.1);
+        }
+
+        // both paths end with the same directories, only their prefix differs
+        let fs_dirs = self.fs_path.ancestors().skip(1);
+        let full_dirs = self.full_path.ancestors().skip(1);
+        let mut packages = fs_dirs
+            .zip(full_dirs)
+            .map(|(fs_dir, full_dir)| {
+                (
+                    fs_dir.join("node_modules").join(request),
+                    full_dir.join("node_modules").join(request),
+                )
+            })
+            .peekable();
+        let attempted = attempted
+            .or_else(|| packages.peek().map(|(_, full_path)| full_path.clone()))
+            .unwrap_or_else(|| self.full_path.clone());
+        packages
+            .find(|(fs_path, _)| fs_path.is_file())
+            .ok_or_else(|| unresolved(attempted))
+    }
+}
+
+/// Symbols of the `@value` declarations and ICSS blocks of a stylesheet
+#[derive(Default)]
+struct ResolvedValues {
+    /// substituted into the stylesheet
+    values: HashMap<String, String>,
+    /// `:import` aliases, replaced by the imported classes where they are used as class selectors
+    class_aliases: HashMap<String, String>,
+    /// added to the class map
+    exports: HashMap<String, String>,
+    /// stylesheets the values are imported from
+    sources: Vec<Dependency>,
+}
+
+/// Expands each class into itself followed by the classes it composes, transitively
+struct ClassNameResolver<'p, 'a> {
+    parser: &'p CssModuleParser<'a>,
+    exports: &'p CssModuleExports,
+    dependencies: &'p HashMap<String, Dependency>,
+    generator: &'p Generator,
+    source: &'p str,
+    /// class names of the exports expanded so far
+    resolved: HashMap<String, Vec<String>>,
+    /// exports being expanded, a local class composing itself is skipped
+    visiting: Vec<String>,
+    diagnostics: Vec<Error>,
+}
+
+impl<'p> ClassNameResolver<'p, '_> {
+    fn resolve(&mut self, name: &str) -> Result<Vec<String>, Error> {
+        if let Some(class_names) = self.resolved.get(name) {
+            return Ok(class_names.clone());
+        }
+        let export = &self.exports[name];
+        let mut class_names = vec![self.local_name(name, export)?];
+
+        self.visiting.push(name.to_string());
+        for reference in &export.composes {
+            match reference {
+                CssModuleReference::Local { name } => match self.local_export(name) {
+                    Some(composed) if self.visiting.contains(composed) => {}
+                    Some(composed) => class_names.extend(self.resolve(composed)?),
+                    None => class_names.push(self.undefined_local_name(name)?),
+                },
+                // global compose need not be transformed
+                CssModuleReference::Global { name } => class_names.push(name.clone()),
+                CssModuleReference::Dependency { name, specifier } => {
+                    let dependency = &self.dependencies[specifier];
+                    let Some(parsed) = &dependency.parsed else {
+                        continue;
+                    };
+                    match parsed.style_name_map.get(name) {
+                        Some(composed) => {
+                            class_names.extend(composed.split(' ').map(str::to_string))
+                        }
+                        None => push_unique(
+                            &mut self.diagnostics,
+                            Error::UnresolvedComposes {
+                                path: self.parser.full_path.clone(),
+                                name: name.clone(),
+                                specifier: specifier.clone(),
+                                dependency: dependency.full_path.clone(),
+                            },
+                        ),
                     }
-                })
-                .collect::<Vec<String>>()
-                .join(" ")
-        )
-        .trim()
-        .to_string()
+                }
+            }
+        }
+        self.visiting.pop();
+
+        self.resolved.insert(name.to_string(), class_names.clone());
+        Ok(class_names)
+    }
+
+    /// Key of the export a local compose refers to,
+    /// lightningcss already names the reference in native mode
+    fn local_export(&self, name: &str) -> Option<&'p String> {
+        if self.parser.is_lightningcss_native() {
+            self.exports
+                .iter()
+                .find(|(_, export)| export.name == name)
+                .map(|(key, _)| key)
+        } else {
+            self.exports.get_key_value(name).map(|(key, _)| key)
+        }
+    }
+
+    /// Name of a local compose without a rule in this stylesheet, scoped like the others,
+    /// lightningcss already names it in native mode
+    fn undefined_local_name(&self, name: &str) -> Result<String, Error> {
+        if self.parser.mode == CssModulesMode::Global || self.parser.is_lightningcss_native() {
+            return Ok(name.to_string());
+        }
+        self.generate(name)
+    }
+
+    fn local_name(&self, name: &str, export: &CssModuleExport) -> Result<String, Error> {
+        if self.parser.mode == CssModulesMode::Global {
+            return Ok(name.to_string());
+        }
+        // lightningcss already names local classes in native mode
+        if self.parser.is_lightningcss_native() {
+            return Ok(export.name.clone());
+        }
+        // like lightningcss, dashed idents keep their dashes in front of the generated name
+        let dashed = export
+            .name
+            .strip_prefix("--")
+            .filter(|_| self.parser.config.css_parser.dashed_idents);
+        match dashed {
+            Some(local) => self.generate(local).map(|name| format!("--{}", name)),
+            None => self.generate(&export.name),
+        }
     }
+
+    /// Generates the name of the unescaped `local`, postcss based presets are given
+    /// the escaped name. generic-names replaces the backslashes of its result, postcss-modules
+    /// result is written as is to the stylesheet and is unescaped
+    fn generate(&self, local: &str) -> Result<String, Error> {
+        let full_path = self.parser.full_path.clone();
+        let preset = self.parser.naming.naming_preset;
+
+        if !preset.escapes_local_name() {
+            return self
+                .generator
+                .generate_with_source(local, full_path, self.source);
+        }
+        let name = self.generator.generate_with_source(
+            &escape_identifier(local),
+            full_path,
+            self.source,
+        )?;
+        Ok(match preset {
+            NamingPreset::PostcssModules => unescape_identifier(&name),
+            _ => name,
+        })
+    }
+}
+
+/// Whether an `@import` points outside of the project, e.g. to a cdn
+fn is_remote_url(url: &str) -> bool {
+    url.starts_with("//") || url.starts_with("data:") || url.contains("://")
+}
+
+/// The same problem can be reached through several classes or stylesheets
+fn push_unique(diagnostics: &mut Vec<Error>, diagnostic: Error) {
+    if !diagnostics.contains(&diagnostic) {
+        diagnostics.push(diagnostic);
+    }
+}
+
+/// Location of the byte `offset` of `source`
+fn location_at(source: &str, offset: usize) -> CssLocation {
+    let before = &source[..offset];
+    let line = before.matches('\n').count();
+    let column = before[before.rfind('\n').map_or(0, |start| start + 1)..]
+        .chars()
+        .count();
+    CssLocation::new(source, line as u32, column as u32 + 1)
+}
+
+fn css_location<T>(source: &str, err: &lightningcss::error::Error<T>) -> Option<CssLocation> {
+    err.loc
+        .as_ref()
+        .map(|loc| CssLocation::new(source, loc.line, loc.column))
+}
+
+/// Source and location of the `index`-th selector of the style rule at `loc`
+fn selector_at(source: &str, loc: Location, index: usize) -> (String, CssLocation) {
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(loc.line as usize)
+        .map(str::len)
+        .sum();
+    // columns are counted in UTF-16 code units
+    let mut start = line_start;
+    let mut column = 1;
+    for c in source[line_start..].chars() {
+        if column >= loc.column {
+            break;
+        }
+        column += c.len_utf16() as u32;
+        start += c.len_utf8();
+    }
+
+    // the selectors end at the block, split on the commas outside of `:is(.a, .b)` or `[a=","]`
+    let mut selectors = vec![start];
+    let mut end = source.len();
+    let mut depth = 0usize;
+    for (offset, c) in source[start..].char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => selectors.push(start + offset + 1),
+            '{' if depth == 0 => {
+                end = start + offset;
+                break;
+            }
+            _ => {}
+        }
+    }
+    selectors.push(end + 1);
+
+    let (from, to) = match (selectors.get(index), selectors.get(index + 1)) {
+        (Some(from), Some(to)) => (*from, to - 1),
+        _ => (start, end),
+    };
+    let selector = &source[from..to];
+    let leading = selector.len() - selector.trim_start().len();
+    (
+        selector.trim().to_string(),
+        location_at(source, from + leading),
+    )
+}
+
+/// Resolves `specifier` relative to the directory of `path`
+fn sibling_path(path: &Path, specifier: &str) -> PathBuf {
+    let path = path.parent().unwrap_or(path).join(specifier);
+    path.absolutize()
+        .map(|path| path.to_path_buf())
+        .unwrap_or(path)
 }
@@ -1,9 +1,17 @@
-use std::{env, fs, path::PathBuf};
+use std::{
+    env, fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 
-use swc_core::ecma::{
-    parser::{EsConfig, Syntax},
-    transforms::testing::test_fixture,
-    visit::as_folder,
+use swc_core::{
+    common::errors::{Diagnostic, DiagnosticBuilder, Emitter, Handler, HandlerFlags, HANDLER},
+    ecma::{
+        ast::Module,
+        parser::{EsConfig, Syntax},
+        transforms::testing::{test_fixture, FixtureTestConfig},
+        visit::{as_folder, Fold, FoldWith},
+    },
 };
 
 use swc_plugin_react_css_modules::{AutoMapCssModules, Config};
@@ -15,6 +23,40 @@ fn syntax() -> Syntax {
     })
 }
 
+/// Collects the diagnostics of the transform
+struct Collect(Arc<Mutex<Vec<Diagnostic>>>);
+
+impl Emitter for Collect {
+    fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+        self.0.lock().unwrap().push((**db).clone());
+    }
+}
+
+/// The handler of `test_fixture` drops warnings, they are collected by a handler
+/// of our own and emitted to it afterwards so that output.stderr lists them too
+struct WithWarnings<F>(F);
+
+impl<F: Fold> Fold for WithWarnings<F> {
+    fn fold_module(&mut self, module: Module) -> Module {
+        let diagnostics = Arc::new(Mutex::new(Vec::new()));
+        let handler = Handler::with_emitter_and_flags(
+            Box::new(Collect(diagnostics.clone())),
+            HandlerFlags {
+                can_emit_warnings: true,
+                ..Default::default()
+            },
+        );
+        let module = HANDLER.set(&handler, || module.fold_with(&mut self.0));
+
+        HANDLER.with(|handler| {
+            for diagnostic in diagnostics.lock().unwrap().drain(..) {
+                DiagnosticBuilder::new_diagnostic(handler, diagnostic).emit();
+            }
+        });
+        module
+    }
+}
+
 #[testing::fixture("tests/fixture/**/input.jsx")]
 fn fixture(input: PathBuf) {
     let output = input.parent().unwrap().join("output.jsx");
@@ -26,17 +68,23 @@ fn fixture(input: PathBuf) {
 
     let cwd = env::current_dir().unwrap();
 
+    // fixtures expecting diagnostics keep them in output.stderr
+    let allow_error = output.with_extension("stderr").exists();
+
     test_fixture(
         syntax(),
         &|_| {
-            as_folder(AutoMapCssModules::new(
+            WithWarnings(as_folder(AutoMapCssModules::new(
                 cwd.to_str().unwrap(),
                 input.to_str().unwrap(),
                 config.clone(),
-            ))
+            )))
         },
         &input,
         &output,
-        Default::default(),
+        FixtureTestConfig {
+            allow_error,
+            ..Default::default()
+        },
     );
-}
\ No newline at end of file
+}
@@ -0,0 +1,5 @@
+import React from 'react';
+import button from './button.module.css';
+import card from './card.module.css';
+
+const Button = () => <button styleName="button.btn button.primary"/>;
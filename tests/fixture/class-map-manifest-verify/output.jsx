@@ -0,0 +1,4 @@
+import React from 'react';
+import button from './button.module.css';
+import card from './card.module.css';
+const Button = ()=><button className="button-module__btn___x7Kq2 button-module__primary___GfBpq"/>;
@@ -0,0 +1,4 @@
+import React from 'react';
+import './button.module.css';
+
+const Button = ({ variant }) => <button styleName="btn primary"><span styleName={variant}/></button>;
@@ -0,0 +1,10 @@
+import React from 'react';
+import './button.module.css';
+import _getClassNames$0 from "swc-plugin-react-css-modules/dist/browser/getClassName";
+const _styleNameObjMap$0 = {
+    "": {
+        "btn": "button_btn_x1",
+        "primary": "button_primary_x2 button_btn_x1"
+    }
+};
+const Button = ({ variant })=><button className="button_btn_x1 button_primary_x2 button_btn_x1"><span className={_getClassNames$0(variant, _styleNameObjMap$0)}/></button>;
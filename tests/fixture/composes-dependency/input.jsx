@@ -0,0 +1,6 @@
+import React from 'react';
+import './styles.css';
+import cycle from './cycle-a.css';
+
+const comp = () => <div styleName="button link" />;
+const cycled = () => <div styleName="cycle.first" />;
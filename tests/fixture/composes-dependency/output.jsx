@@ -0,0 +1,5 @@
+import React from 'react';
+import './styles.css';
+import cycle from './cycle-a.css';
+const comp = ()=><div className="styles__button base__base base__root focus__focus reset__reset styles__link base__underline"/>;
+const cycled = ()=><div className="cycle-a__first cycle-b__second"/>;
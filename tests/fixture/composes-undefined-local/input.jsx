@@ -0,0 +1,4 @@
+import React from 'react';
+import './styles.css';
+
+const Button = () => <button styleName="button" />;
@@ -0,0 +1,3 @@
+import React from 'react';
+import './styles.css';
+const Button = ()=><button className="styles__button styles__base"/>;
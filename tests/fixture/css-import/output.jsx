@@ -0,0 +1,3 @@
+import React from 'react';
+import './styles.css';
+const comp = ()=><div className="colors__base-button colors__primary colors__primary styles__title buttons__button"/>;
@@ -0,0 +1,11 @@
+import React from 'react';
+import utilities from './utilities.global.css';
+import tokens from './tokens.icss.css';
+import card from './src/components/card.css';
+
+const Card = () => (
+  <div styleName="utilities.stack card.card">
+    <h2 styleName="card.title" data-brand="tokens.brand" />
+    <span styleName="tokens.brand tokens.unused" />
+  </div>
+);
@@ -0,0 +1,11 @@
+import React from 'react';
+import utilities from './utilities.global.css';
+import tokens from './tokens.icss.css';
+import card from './src/components/card.css';
+const Card = ()=><div className="stack flex card__card">
+
+    <h2 data-brand="tokens.brand" className="card__title"/>
+
+    <span className="#0af"/>
+
+  </div>;
@@ -0,0 +1,4 @@
+import React from 'react';
+import './styles.css';
+
+const comp = () => <div styleName="card card-title card-compact after-broken"/>
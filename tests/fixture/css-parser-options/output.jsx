@@ -0,0 +1,3 @@
+import React from 'react';
+import './styles.css';
+const comp = ()=><div className="styles__card_VX4U7 styles__card-title_uscfq styles__card-compact_g2xIu styles__after-broken_iduz3"/>;
@@ -0,0 +1,4 @@
+import React from 'react';
+import './styles.css';
+
+const comp = () => <div styleName="card primary card-small" />;
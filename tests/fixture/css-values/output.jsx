@@ -0,0 +1,3 @@
+import React from 'react';
+import './styles.css';
+const comp = ()=><div className="styles__card #0af styles__card-small"/>;
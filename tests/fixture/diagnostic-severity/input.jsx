@@ -0,0 +1,5 @@
+import React from 'react';
+import base from './base.css';
+import theme from './theme.css';
+
+const comp = () => <div styleName="title missing foo.bar"/>
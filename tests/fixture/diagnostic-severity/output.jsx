@@ -0,0 +1,4 @@
+import React from 'react';
+import base from './base.css';
+import theme from './theme.css';
+const comp = ()=><div className="base__title_ls32a"/>;
@@ -0,0 +1,13 @@
+import React from 'react';
+import genericNames from './generic-names/utilities.css';
+import cssLoader from './css-loader/utilities.css';
+import postcssModules from './postcss-modules/utilities.css';
+import lightningcss from './lightningcss/utilities.css';
+import './spacing.css';
+
+const GenericNames = () => <div styleName="genericNames.sm:hidden genericNames.w-1/2 genericNames.10px genericNames.mt-2.5 genericNames.card"/>;
+const CssLoader = () => <div styleName="cssLoader.sm:hidden cssLoader.w-1/2 cssLoader.10px cssLoader.mt-2.5 cssLoader.card"/>;
+const PostcssModules = () => <div styleName="postcssModules.sm:hidden postcssModules.w-1/2 postcssModules.10px postcssModules.mt-2.5"/>;
+const Lightningcss = () => <div styleName="lightningcss.sm:hidden lightningcss.w-1/2 lightningcss.10px lightningcss.mt-2.5"/>;
+const Spacing = () => <div styleName="space-x-0.5"/>;
+const Dynamic = ({ size }) => <div styleName={size}/>;
@@ -0,0 +1,46 @@
+import React from 'react';
+import genericNames from './generic-names/utilities.css';
+import cssLoader from './css-loader/utilities.css';
+import postcssModules from './postcss-modules/utilities.css';
+import lightningcss from './lightningcss/utilities.css';
+import './spacing.css';
+import _getClassNames$0 from "swc-plugin-react-css-modules/dist/browser/getClassName";
+const _styleNameObjMap$0 = {
+    "": {
+        "space-x-0.5": "spacing__space-x-0--5___-FB5I"
+    },
+    "cssLoader": {
+        "10px": "utilities__10px--SDXB5",
+        "card": "utilities__card--bLU4v utilities__sm:hidden--AKMGa",
+        "mt-2.5": "utilities__mt-2.5--MpACT",
+        "sm:hidden": "utilities__sm:hidden--AKMGa",
+        "w-1/2": "utilities__w-1/2--rnWcs"
+    },
+    "genericNames": {
+        "10px": "utilities__-31-0px___M5jpd",
+        "card": "utilities__card___mWY56 utilities__sm--hidden___Qjdt5",
+        "mt-2.5": "utilities__mt-2--5___6V-uU",
+        "sm:hidden": "utilities__sm--hidden___Qjdt5",
+        "w-1/2": "utilities__w-1--2___uE-au"
+    },
+    "lightningcss": {
+        "10px": "utilities_10px_xQKBOa",
+        "card": "utilities_card_xQKBOa utilities_sm:hidden_xQKBOa",
+        "mt-2.5": "utilities_mt-2.5_xQKBOa",
+        "sm:hidden": "utilities_sm:hidden_xQKBOa",
+        "w-1/2": "utilities_w-1/2_xQKBOa"
+    },
+    "postcssModules": {
+        "10px": "_10px_drh2q_9",
+        "card": "_card_drh2q_17 _sm:hidden_drh2q_1",
+        "mt-2.5": "_mt-2.5_drh2q_13",
+        "sm:hidden": "_sm:hidden_drh2q_1",
+        "w-1/2": "_w-1/2_drh2q_5"
+    }
+};
+const GenericNames = ()=><div className="utilities__sm--hidden___Qjdt5 utilities__w-1--2___uE-au utilities__-31-0px___M5jpd utilities__mt-2--5___6V-uU utilities__card___mWY56 utilities__sm--hidden___Qjdt5"/>;
+const CssLoader = ()=><div className="utilities__sm:hidden--AKMGa utilities__w-1/2--rnWcs utilities__10px--SDXB5 utilities__mt-2.5--MpACT utilities__card--bLU4v utilities__sm:hidden--AKMGa"/>;
+const PostcssModules = ()=><div className="_sm:hidden_drh2q_1 _w-1/2_drh2q_5 _10px_drh2q_9 _mt-2.5_drh2q_13"/>;
+const Lightningcss = ()=><div className="utilities_sm:hidden_xQKBOa utilities_w-1/2_xQKBOa utilities_10px_xQKBOa utilities_mt-2.5_xQKBOa"/>;
+const Spacing = ()=><div className="spacing__space-x-0--5___-FB5I"/>;
+const Dynamic = ({ size })=><div className={_getClassNames$0(size, _styleNameObjMap$0)}/>;
@@ -0,0 +1,5 @@
+import React from 'react';
+import menu from './menu.css';
+
+const Menu = ({ open }) => <nav styleName="menu.menu is-open" />;
+const Themed = () => <body styleName="theme-dark" />;
@@ -0,0 +1,4 @@
+import React from 'react';
+import menu from './menu.css';
+const Menu = ({ open })=><nav className="menu__menu is-open"/>;
+const Themed = ()=><body className="theme-dark"/>;
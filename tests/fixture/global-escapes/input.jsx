@@ -0,0 +1,5 @@
+import React from 'react';
+import './card.css';
+
+const Card = () => <div className="shadow" styleName="card :global(flex) global.mt-2.5" />;
+const Dynamic = ({ gap }) => <div styleName={`card global.gap-${gap}`} />;
@@ -0,0 +1,10 @@
+import React from 'react';
+import './card.css';
+import _getClassNames$0 from "swc-plugin-react-css-modules/dist/browser/getClassName";
+const _styleNameObjMap$0 = {
+    "": {
+        "card": "card__card"
+    }
+};
+const Card = ()=><div className="shadow card__card flex mt-2.5"/>;
+const Dynamic = ({ gap })=><div className={_getClassNames$0(`card global.gap-${gap}`, _styleNameObjMap$0)}/>;
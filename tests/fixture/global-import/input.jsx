@@ -0,0 +1,5 @@
+import React from 'react';
+import global from './card.css';
+
+const Card = () => <div styleName="global.card :global(flex)" />;
+const Dynamic = ({ size }) => <div styleName={`global.card global.${size}`} />;
@@ -0,0 +1,10 @@
+import React from 'react';
+import global from './card.css';
+import _getClassNames$0 from "swc-plugin-react-css-modules/dist/browser/getClassName";
+const _styleNameObjMap$0 = {
+    "global": {
+        "card": "card__card"
+    }
+};
+const Card = ()=><div className="card__card flex"/>;
+const Dynamic = ({ size })=><div className={_getClassNames$0(`global.card global.${size}`, _styleNameObjMap$0)}/>;
@@ -0,0 +1,6 @@
+import React from 'react';
+import './styles.css';
+
+const comp = () => <div styleName="card icon gridGutter baseButton baseSpacing" />;
+
+const alias = () => <div styleName="imported-button" />;
@@ -0,0 +1,4 @@
+import React from 'react';
+import './styles.css';
+const comp = ()=><div className="styles__card styles__icon 16px base__button 8px"/>;
+const alias = ()=><div className="base__button"/>;
@@ -0,0 +1,4 @@
+import React from 'react';
+import './a.css';
+
+const comp = () => <div styleName="a b" />;
@@ -0,0 +1,3 @@
+import React from 'react';
+import './a.css';
+const comp = ()=><div className="a__a b__b"/>;
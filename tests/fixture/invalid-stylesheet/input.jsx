@@ -0,0 +1,5 @@
+import React from 'react';
+import broken from './broken.css';
+import './styles.css';
+
+const comp = () => <div styleName="another-thing"/>
\ No newline at end of file
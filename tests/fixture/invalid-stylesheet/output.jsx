@@ -0,0 +1,4 @@
+import React from 'react';
+import broken from './broken.css';
+import './styles.css';
+const comp = ()=><div className="styles__another-thing_PIp2s"/>;
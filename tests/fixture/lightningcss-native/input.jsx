@@ -0,0 +1,4 @@
+import React from 'react';
+import './styles.module.css';
+
+const comp = () => <button styleName="button"/>
\ No newline at end of file
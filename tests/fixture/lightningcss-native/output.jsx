@@ -0,0 +1,4 @@
+import React from 'react';
+import './styles.module.css';
+
+const comp = () => <button className="x2S0iG_button x2S0iG_base"/>
\ No newline at end of file
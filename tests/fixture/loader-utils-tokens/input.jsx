@@ -0,0 +1,6 @@
+import React from 'react';
+import styles from './styles.css';
+import dark from './styles.css?theme=dark';
+
+const Button = () => <button styleName="styles.button" />;
+const DarkButton = () => <button styleName="dark.button" />;
@@ -0,0 +1,5 @@
+import React from 'react';
+import styles from './styles.css';
+import dark from './styles.css?theme=dark';
+const Button = ()=><button className="loader-utils-tokens_fixture-loader-utils-tokens-styles__button"/>;
+const DarkButton = ()=><button className="loader-utils-tokens_fixture-loader-utils-tokens-styles__button-theme-dark"/>;
@@ -0,0 +1,4 @@
+import React from 'react';
+import './styles.css';
+
+const comp = () => <div className="something" styleName="another-thing"/>
\ No newline at end of file
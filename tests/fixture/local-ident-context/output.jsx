@@ -0,0 +1,4 @@
+import React from 'react';
+import './styles.css';
+
+const comp = () => <div className="something styles__another-thing_Khwl5"/>
\ No newline at end of file
@@ -0,0 +1,10 @@
+import React from 'react';
+import { scopedName } from 'swc-plugin-react-css-modules/dist/macro';
+import * as macros from 'swc-plugin-react-css-modules/dist/macro';
+import './styles.css';
+const Title = ()=><h2 id={"styles__title"}>Title</h2>;
+const Fade = ()=><div style={{
+        animationName: "styles__fade"
+    }}/>;
+const Dynamic = (name)=>scopedName(name);
+const Namespaced = ()=>macros.scopedName('title');
@@ -0,0 +1,4 @@
+import React from 'react';
+import './missing.css';
+import './styles.css';
+const comp = ()=><div className="styles__another-thing_PnPSY"/>;
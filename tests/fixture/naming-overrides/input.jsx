@@ -0,0 +1,5 @@
+import React from 'react';
+import './styles.css';
+import legacy from './legacy-ui/button.css';
+
+const comp = () => <div styleName="title legacy.button"/>
\ No newline at end of file
@@ -0,0 +1,5 @@
+import React from 'react';
+import './styles.css';
+import legacy from './legacy-ui/button.css';
+
+const comp = () => <div className="styles__title_jEAdr legacy_button_WtLx"/>
\ No newline at end of file
@@ -0,0 +1,4 @@
+import React from 'react';
+import './card.module.css';
+
+const comp = () => <div styleName="card-title"/>
\ No newline at end of file
@@ -0,0 +1,4 @@
+import React from 'react';
+import './card.module.css';
+
+const comp = () => <div className="card_card-title__z2Itx"/>
\ No newline at end of file
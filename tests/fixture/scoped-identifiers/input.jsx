@@ -0,0 +1,15 @@
+import React from 'react';
+import { scopedName } from 'swc-plugin-react-css-modules/dist/macro';
+import styles from './styles.css';
+
+const Icon = () => (
+  <svg styleName="card">
+    <use href={`#${scopedName('icon-star', 'id')}`} />
+  </svg>
+);
+const Title = () => <h2 idName="title" className="heading">Stars</h2>;
+const Dialog = () => (
+  <div role="dialog" labelledBy="styles.title" style={{ animationName: scopedName('styles.fade', 'keyframes') }} />
+);
+const Mismatch = () => <div style={{ animationName: scopedName('card', 'keyframes') }} />;
+const Invalid = (name) => scopedName(name);
@@ -0,0 +1,16 @@
+import React from 'react';
+import { scopedName } from 'swc-plugin-react-css-modules/dist/macro';
+import styles from './styles.css';
+const Icon = ()=><svg className="styles__card">
+
+    <use href={`#${"styles__icon-star"}`}/>
+
+  </svg>;
+const Title = ()=><h2 className="heading" id="styles__title">Stars</h2>;
+const Dialog = ()=><div role="dialog" style={{
+        animationName: "styles__fade"
+    }} aria-labelledby="styles__title"/>;
+const Mismatch = ()=><div style={{
+        animationName: "styles__card"
+    }}/>;
+const Invalid = (name)=>scopedName(name);
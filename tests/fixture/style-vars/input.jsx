@@ -0,0 +1,11 @@
+import React from 'react';
+import styles from './button.css';
+
+const Button = ({ color }) => <button styleName="button" styleVars={{ accent: color }} />;
+const Outlined = ({ color, style }) => (
+  <button styleName="button" style={style} styleVars={{ 'styles.border': color, accent: 'red' }} />
+);
+const Merged = ({ border }) => <button style={{ margin: 0 }} styleVars={{ border }} />;
+const Dynamic = ({ vars }) => <button styleVars={{ ...vars, missing: 1 }} />;
+const Unresolved = () => <button styleVars={{ missing: 1 }} />;
+const Kept = ({ style }) => <button style={style} styleVars={{ missing: 1 }} />;
@@ -0,0 +1,19 @@
+import React from 'react';
+import styles from './button.css';
+const Button = ({ color })=><button className="button__button" style={{
+        "--button__accent": color
+    }}/>;
+const Outlined = ({ color, style })=><button className="button__button" style={{
+        ...style,
+        "--button__border": color,
+        "--button__accent": 'red'
+    }}/>;
+const Merged = ({ border })=><button style={{
+        margin: 0,
+        "--button__border": border
+    }}/>;
+const Dynamic = ({ vars })=><button style={{
+        ...vars
+    }}/>;
+const Unresolved = ()=><button/>;
+const Kept = ({ style })=><button style={style}/>;
@@ -0,0 +1,6 @@
+import React from 'react';
+import './styles.css';
+import card from './card.css';
+
+const comp = () => <div styleName="another-thing card-heder card.card-headr foo.bar"/>
+const shadow = () => <div className="shadow" styleName="card-heder"/>
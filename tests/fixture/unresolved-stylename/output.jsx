@@ -0,0 +1,5 @@
+import React from 'react';
+import './styles.css';
+import card from './card.css';
+const comp = ()=><div className="styles__another-thing_2hdxQ"/>;
+const shadow = ()=><div className="shadow"/>;